@@ -0,0 +1,121 @@
+// Time spent in `request_frame` with and without batching, in Node, on a canvas that counts its calls
+// but draws nothing: it measures what wasm and the bindings cost per frame, not rasterisation.
+// It loads the module straight from cargo, before wasm-bindgen generates its glue, so it stands in
+// for that glue with the few imports rendering needs
+//
+//     cargo build --release --lib --target wasm32-unknown-unknown
+//     node js/bench/render.cjs target/wasm32-unknown-unknown/release/boids.wasm [boids] [frames] [color mode]
+
+const fs = require('fs')
+
+const [path, boids = 5000, frames = 100, colorMode] = process.argv.slice(2).map((arg, i) => i === 1 || i === 2 ? Number(arg) : arg)
+
+// JS values handed to wasm, by index, as the generated glue keeps them
+const heap = [undefined, null, true, false]
+const freed = []
+function keep(value) {
+	const index = freed.length ? freed.pop() : heap.length
+	heap[index] = value
+	return index
+}
+
+let memory
+const decoder = new TextDecoder()
+const string = (pointer, length) => decoder.decode(new Uint8Array(memory.buffer, pointer, length))
+const encoder = new TextEncoder()
+
+let calls = {}
+const count = name => calls[name] = (calls[name] || 0) + 1
+const context = {}
+for(const name of ['beginPath', 'clearRect', 'closePath', 'arc', 'fill', 'stroke', 'lineTo', 'moveTo'])
+	context[name] = () => count(name)
+for(const name of ['fillStyle', 'strokeStyle', 'globalAlpha'])
+	Object.defineProperty(context, name, {set: () => count(name), get: () => 1})
+
+const imports = {
+	object_drop_ref: index => {
+		heap[index] = undefined
+		freed.push(index)
+	},
+	now: () => performance.now(),
+	random: () => Math.random(),
+	log: () => {},
+	error: (pointer, length) => console.error(length === undefined ? heap[pointer] : string(pointer, length)),
+	new: (pointer, length) => keep(length === undefined ? new Error() : new Error(string(pointer, length))),
+	set_name: (error, pointer, length) => heap[error].name = string(pointer, length),
+	set: (target, key, value) => Number(Reflect.set(heap[target], heap[key], heap[value])),
+	stack: () => {},
+	beginPath: c => heap[c].beginPath(),
+	clearRect: (c, x, y, width, height) => heap[c].clearRect(x, y, width, height),
+	closePath: c => heap[c].closePath(),
+	globalAlpha: c => heap[c].globalAlpha,
+	set_globalAlpha: (c, alpha) => heap[c].globalAlpha = alpha,
+	set_fillStyle: (c, pointer, length) => heap[c].fillStyle = string(pointer, length),
+	set_strokeStyle: (c, pointer, length) => heap[c].strokeStyle = string(pointer, length),
+	arc: (c, x, y, radius, start, end) => heap[c].arc(x, y, radius, start, end),
+	fill: c => heap[c].fill(),
+	stroke: c => heap[c].stroke(),
+	lineTo: (c, x, y) => heap[c].lineTo(x, y),
+	moveTo: (c, x, y) => heap[c].moveTo(x, y),
+	__wbindgen_throw: (pointer, length) => {
+		throw new Error(string(pointer, length))
+	}
+}
+
+// imports are named after the JS function they wrap, followed by a hash
+const placeholder = new Proxy({}, {get(_, name) {
+	if(name.startsWith('__wbindgen_describe') || name.startsWith('__wbindgen_externref'))
+		return () => 0
+	const key = name.replace(/^__wbindgen_/, '').replace(/^__wbg_/, '').replace(/_[0-9a-f]{16}$/, '')
+	const found = imports[key] || imports[key.replace(/_[0-9a-f]{16}$/, '')] || (key.startsWith('error') && imports.error)
+	return found || (() => {
+		throw new Error(`${name} isn't provided`)
+	})
+}})
+const compiled = new WebAssembly.Module(fs.readFileSync(path))
+const wasm = new WebAssembly.Instance(compiled, {
+	__wbindgen_placeholder__: placeholder,
+	__wbindgen_externref_xform__: placeholder
+}).exports
+memory = wasm.memory
+
+// exports also carry a hash. Fallible ones write behind a pointer their value, if any,
+// then the index of the error and whether there was one
+const result = wasm.__wbindgen_malloc(16, 8)
+const exported = Object.fromEntries(Object.keys(wasm).map(key => [key.replace(/_[0-9a-f]{16}$/, ''), wasm[key]]))
+function call(name, returnsValue, ...args) {
+	exported[name](result, ...args)
+	const words = new Uint32Array(memory.buffer, result, 3)
+	const [value, error, failed] = returnsValue ? words : [undefined, ...words]
+	if(failed)
+		throw heap[error]
+	return value
+}
+const text = value => {
+	const bytes = encoder.encode(value)
+	const pointer = wasm.__wbindgen_malloc(bytes.length, 1)
+	new Uint8Array(memory.buffer, pointer, bytes.length).set(bytes)
+	return [pointer, bytes.length]
+}
+
+call('send_context', true, keep(context), 1920, 1080)
+call('add_boids', true, Math.max(0, boids - call('get_boids_count', true)))
+if(colorMode)
+	call('set_color_mode', false, ...text(colorMode))
+// let the flock gather, every frame then draws the same one
+for(let i = 0; i < 20; i++)
+	call('request_tick', false, 16, 0)
+
+for(const batched of [false, true]) {
+	call('set_batched', false, batched)
+	calls = {}
+	const times = []
+	for(let i = 0; i < frames; i++) {
+		const start = performance.now()
+		call('request_frame', false, 16, 0, 0)
+		times.push(performance.now() - start)
+	}
+	times.sort((a, b) => a - b)
+	const perFrame = Object.fromEntries(Object.entries(calls).map(([name, total]) => [name, total / frames]))
+	console.log(`${batched ? 'batched ' : 'per boid'}: ${times[frames >> 1].toFixed(2)} ms/frame (median), calls per frame ${JSON.stringify(perFrame)}`)
+}
@@ -1,4 +0,0 @@
-const channel = new BroadcastChannel('wasm interop')
-export function sendMessage(...args) {
-    channel.postMessage({args})
-}
\ No newline at end of file
@@ -0,0 +1,15 @@
+// Floats per boid: x0, y0, x1, y1, x2, y2, colour index (same as `render::STRIDE`)
+export const STRIDE = 7
+
+// Reads the boids straight from wasm memory, without a call per boid.
+// `memory` is the `memory` export of the instance resolved by `wasm.default()`.
+// The views are only valid until the next call: growing the buffer may move it
+export function readRenderBuffer(wasm, memory) {
+	const count = wasm.update_render_buffer()
+	return {
+		count,
+		triangles: new Float32Array(memory.buffer, wasm.render_buffer_ptr(), wasm.render_buffer_len()),
+		// 0xRRGGBBAA, indexed by the last float of each boid
+		colors: new Uint32Array(memory.buffer, wasm.render_colors_ptr(), wasm.render_colors_len())
+	}
+}
@@ -3,18 +3,26 @@ import * as wasm from "../pkg/boids.js"
 let DEBUG = false
 let TIE_UPDATES_TO_FRAMES = true
 let FIELD_OF_VIEW = false
+let DRAGGING = undefined
+let JUST_DRAGGED = false
 let ctx
+let BOIDS = 0
 
-const channel = new BroadcastChannel('wasm interop')
-channel.onmessage = ({data}) => {
-	if(data.args.length === 2) {
-		const [key, value] = data.args
-		if(key === "frame")
-			self.postMessage({frame: value})
-		if(key === "tick")
-			self.postMessage({update: value})
-	} else {
-		console.log(...data.args)
+function onEvent(event) {
+	switch(event.type) {
+		case 'frame':
+			self.postMessage({frame: event.deltaTime})
+			break
+		case 'tick':
+			self.postMessage({update: event.deltaTime})
+			break
+		case 'boidAdded':
+		case 'boidRemoved':
+			self.postMessage({count: event.count})
+			break
+		case 'error':
+			console.error(`${event.name} (${event.code}): ${event.message}`)
+			break
 	}
 }
 
@@ -23,6 +31,7 @@ function onCanvasMessage(resolve) {
 		if('canvas' in event.data) {
 			const canvas = event.data.canvas
 			ctx = canvas.getContext('2d')
+			BOIDS = event.data.boids
 			resolve()
 			self.removeEventListener('message', onMessage)
 		}
@@ -31,15 +40,17 @@ function onCanvasMessage(resolve) {
 }
 
 const ready = Promise.all([
-	wasm.default(),
+	// initThreadPool is only exported by builds with the `parallel` feature
+	wasm.default().then(() => wasm.initThreadPool?.(navigator.hardwareConcurrency)),
 	new Promise(onCanvasMessage)
 ])
 
 ready.then(() => {
 	console.log(wasm, ctx)
 	wasm.console_log('READY')
+	wasm.set_event_listener(onEvent)
 	wasm.send_context(ctx, ctx.canvas.width, ctx.canvas.height)
-	const count = wasm.get_boids_count()
+	const count = wasm.add_boids(Math.max(0, BOIDS - wasm.get_boids_count()))
 	postMessage({count})
 	init(wasm, ctx)
 	loopFrame(wasm.request_frame, wasm.request_tick)
@@ -50,6 +61,7 @@ function loopFrame(callback, preCallback, start = performance.now()) {
 	requestAnimationFrame(() => {
 		const time = performance.now()
 		callback(time - start, FIELD_OF_VIEW, DEBUG)
+		self.postMessage({render: performance.now() - time})
 		if (!DEBUG) {
 			setTimeout(() => {
 				if(TIE_UPDATES_TO_FRAMES)
@@ -74,9 +86,40 @@ function loopTick(callback, start = performance.now()) {
 
 function init(wasm, ctx) {
 	self.onmessage = function(event) {
-		if(event.data.new && 'x' in event.data && 'y' in event.data) {
-			const count = wasm.add_one_boid(event.data.x, event.data.y)
-			postMessage({count})
+		if(event.data.new && 'x' in event.data && 'y' in event.data && !JUST_DRAGGED) {
+			wasm.add_one_boid(event.data.x, event.data.y)
+		}
+
+		if('attractor' in event.data) {
+			wasm.add_attractor(event.data.x, event.data.y, event.data.attractor, 300)
+		}
+
+		if(event.data.waypoint) {
+			wasm.add_waypoint(event.data.x, event.data.y)
+		}
+
+		// a double click removes the attractor or else the boid under the mouse, and on empty space the waypoints
+		if(event.data.remove) {
+			const id = wasm.find_attractor(event.data.x, event.data.y)
+			if(id !== undefined)
+				wasm.remove_attractor(id)
+			else if(wasm.remove_boid(event.data.x, event.data.y) === undefined)
+				wasm.clear_waypoints()
+		}
+
+		if('grab' in event.data) {
+			if(event.data.grab) {
+				DRAGGING = wasm.find_attractor(event.data.x, event.data.y)
+				JUST_DRAGGED = false
+			} else {
+				DRAGGING = undefined
+			}
+		}
+
+		// only an attractor that actually moved swallows the click ending the drag
+		if(event.data.drag && DRAGGING !== undefined) {
+			wasm.move_attractor(DRAGGING, event.data.x, event.data.y)
+			JUST_DRAGGED = true
 		}
 
 		if('height' in event.data || 'width' in event.data) {
@@ -93,6 +136,82 @@ function init(wasm, ctx) {
 			FIELD_OF_VIEW = event.data.view
 		}
 
+		if('batched' in event.data) {
+			wasm.set_batched(event.data.batched)
+		}
+
+		if('space' in event.data) {
+			wasm.set_space(event.data.space)
+		}
+
+		if('occlusion' in event.data) {
+			wasm.set_occlusion(event.data.occlusion)
+		}
+
+		if('color' in event.data) {
+			wasm.set_color_mode(event.data.color)
+		}
+
+		if('neighbourhood' in event.data) {
+			wasm.set_neighbourhood(event.data.neighbourhood)
+		}
+
+		if('alignment' in event.data) {
+			wasm.set_alignment(event.data.alignment)
+		}
+
+		if('direction' in event.data) {
+			wasm.set_rule_weight('follow_group', event.data.direction)
+		}
+
+		if('avoidance' in event.data) {
+			wasm.set_rule_weight('avoid_entities', event.data.avoidance)
+		}
+
+		if('flocking' in event.data) {
+			wasm.set_rule_weight('go_to_group', event.data.flocking)
+		}
+
+		if('steering' in event.data) {
+			wasm.set_steering(event.data.steering)
+		}
+
+		if('flow' in event.data) {
+			const {width, height} = ctx.canvas
+			if(event.data.flow === 'wind')
+				wasm.set_wind(0.5, 0.1)
+			else if(event.data.flow === 'vortex')
+				wasm.set_vortex(width / 2, height / 2, 1, Math.min(width, height) / 3)
+			else if(event.data.flow === 'noise')
+				wasm.set_flow_noise(0.003, 0.6, 0.002, 0)
+			else
+				wasm.clear_flow()
+		}
+
+		if('mask' in event.data) {
+			wasm.set_mask(new Uint8Array(event.data.mask), event.data.maskWidth, event.data.maskHeight, 128)
+		}
+
+		if('arena' in event.data) {
+			const {width, height} = ctx.canvas
+			if(event.data.arena === 'circle') {
+				wasm.set_circular_arena()
+			} else if(event.data.arena === 'room') {
+				// L-shaped room with a square pillar
+				const room = [
+					[50, 50], [width - 50, 50], [width - 50, height / 2],
+					[width / 2, height / 2], [width / 2, height - 50], [50, height - 50],
+				]
+				const pillar = [
+					[width / 4 - 50, height / 4 - 50], [width / 4 + 50, height / 4 - 50],
+					[width / 4 + 50, height / 4 + 50], [width / 4 - 50, height / 4 + 50],
+				]
+				wasm.set_arena(new Float64Array([...room, ...pillar].flat()), new Uint32Array([room.length, pillar.length]))
+			} else {
+				wasm.clear_arena()
+			}
+		}
+
 		if('debug' in event.data) {
 			DEBUG = event.data.debug
 			// if(!DEBUG)
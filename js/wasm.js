@@ -4,23 +4,72 @@ canvas.width = innerWidth
 document.body.appendChild(canvas)
 const worker = new Worker("js/rustWorker.js", { type: "module" })
 const offscreen = canvas.transferControlToOffscreen()
-worker.postMessage({canvas: offscreen}, [offscreen])
+// index.html?boids=10000 tops the starting flock up to that many boids
+const boids = Number(new URLSearchParams(location.search).get('boids')) || 0
+worker.postMessage({canvas: offscreen, boids}, [offscreen])
 
 window.addEventListener('resize', () => worker.postMessage({height: innerHeight, width: innerWidth}))
-canvas.addEventListener('click', ({x, y}) => worker.postMessage({new: true, x, y}))
+// a double click starts with two clicks, which must not add boids: single clicks wait to be sure
+const DOUBLE_CLICK_DELAY = 250
+let pendingClick
+canvas.addEventListener('click', ({x, y, shiftKey, altKey, ctrlKey, metaKey, detail}) => {
+	clearTimeout(pendingClick)
+	if(detail > 1)
+		return
+	pendingClick = setTimeout(() => {
+		if(shiftKey || altKey)
+			worker.postMessage({attractor: shiftKey ? 1 : -1, x, y})
+		else if(ctrlKey || metaKey)
+			worker.postMessage({waypoint: true, x, y})
+		else
+			worker.postMessage({new: true, x, y})
+	}, DOUBLE_CLICK_DELAY)
+})
+canvas.addEventListener('dblclick', ({x, y}) => {
+	clearTimeout(pendingClick)
+	worker.postMessage({remove: true, x, y})
+})
+canvas.addEventListener('mousedown', ({x, y}) => worker.postMessage({grab: true, x, y}))
+canvas.addEventListener('mousemove', ({x, y, buttons}) => buttons && worker.postMessage({drag: true, x, y}))
+canvas.addEventListener('mouseup', () => worker.postMessage({grab: false}))
+
+// drop an image on the canvas to use its dark pixels as obstacles
+canvas.addEventListener('dragover', event => event.preventDefault())
+canvas.addEventListener('drop', async event => {
+	event.preventDefault()
+	const file = event.dataTransfer.files[0]
+	if(!file)
+		return
+	const bitmap = await createImageBitmap(file)
+	const context = new OffscreenCanvas(bitmap.width, bitmap.height).getContext('2d')
+	context.drawImage(bitmap, 0, 0)
+	const {data, width, height} = context.getImageData(0, 0, bitmap.width, bitmap.height)
+	worker.postMessage({mask: data.buffer, maskWidth: width, maskHeight: height}, [data.buffer])
+})
 
 void [
-	// 'direction',
-	// 'avoidance',
-	// 'flocking',
+	'direction',
+	'avoidance',
+	'flocking',
 	'debug',
 	'tick',
-	'view'
+	'view',
+	'batched',
+	'space',
+	'occlusion',
+	'color',
+	'alignment',
+	'steering',
+	'neighbourhood',
+	'flow',
+	'arena'
 ].forEach(key => {
 	const input = document.getElementById(key)
 	input.addEventListener(input.type === "range" ? "input" : "change", ({target}) => {
 		let value = input.type === "range"
 			? target.value / 100
+			: input.tagName === "SELECT"
+			? target.value
 			: target.checked
 		worker.postMessage({[key]: value})
 		target.nextElementSibling.innerText = value
@@ -29,6 +78,7 @@ void [
 
 let frames = []
 let updates = []
+let renders = []
 worker.addEventListener('message', ({data}) => {
 	if('count' in data) {
 		document.getElementById('count').innerText = data.count + ' boids'
@@ -42,6 +92,15 @@ worker.addEventListener('message', ({data}) => {
 		document.getElementById('fps').innerText = fps + ' fps'
 	}
 
+	// time spent drawing, to compare batched drawing against one fill per boid
+	if('render' in data) {
+		renders.push(data.render)
+		if(renders.length > 100)
+			renders.splice(0, renders.length - 100)
+		const average = renders.reduce((sum, curr) => sum + curr, 0) / renders.length
+		document.getElementById('render').innerText = average.toFixed(2) + ' ms/frame'
+	}
+
 	if('update' in data) {
 		updates.push(data.update)
 		if(updates.length > 100)
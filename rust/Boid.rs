@@ -1,11 +1,170 @@
 use std::f64::consts::PI;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
+use crate::angle::Angle;
+use crate::vec2::Vec2;
+use crate::arena::{Arena, Hit};
+use crate::mask::Mask;
+use crate::circular;
+use crate::flock::{Flock, Traits};
+use crate::color::{ColorMode, Rgba, PALETTE};
+use crate::flow::Flow;
+use crate::goal::{turn_towards, Goals};
+use crate::profile::Counters;
+use crate::reynolds::{Body, Steer};
+use crate::rules::Pipeline;
+
+/// How far short of a wall a boid stops when its move would cross it
+const WALL_GAP: f64 = 0.5;
 
 pub struct Canvas {
 	pub width: f64,
 	pub height: f64,
-	pub padding: f64
+	pub padding: f64,
+	/// walls boids stay within, instead of the edges of the canvas
+	pub arena: Option<Arena>,
+	/// obstacles painted in an image
+	pub mask: Option<Mask>
+}
+
+impl Canvas {
+	/// Inside the arena, if any, and not on a solid pixel of the mask, if any
+	pub fn is_open(&self, x: f64, y: f64) -> bool {
+		self.arena.as_ref().is_none_or(|arena| arena.contains(x, y))
+			&& self.mask.as_ref().is_none_or(|mask| !mask.is_solid(x, y, self))
+	}
+
+	fn has_obstacles(&self) -> bool {
+		self.arena.is_some() || self.mask.is_some()
+	}
+
+	/// Closest arena wall or mask obstacle on the segment between two points
+	fn first_hit(&self, from: &Point, to: &Point) -> Option<Hit> {
+		let offset = *to - *from;
+		let distance = offset.length();
+		let direction = offset.normalise()?;
+		let direction = (direction.x, direction.y);
+		let arena = self.arena.as_ref().and_then(|arena| arena.cast(from.x, from.y, direction, distance));
+		let mask = self.mask.as_ref().and_then(|mask| mask.cast(from.x, from.y, direction, distance, self));
+		arena.into_iter().chain(mask).min_by(|a, b| a.distance.total_cmp(&b.distance))
+	}
+
+	/// Whether an arena wall or a mask obstacle stands between two points
+	fn blocks(&self, from: &Point, to: &Point) -> bool {
+		self.first_hit(from, to).is_some()
+	}
+}
+
+/// Statistic used to find the heading of visible flockmates
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Alignment {
+	Mean,
+	WeightedMean,
+	/// robust to outlier headings, see `circular::median`
+	Median
+}
+
+impl Alignment {
+	pub fn from_name(name: &str) -> Option<Alignment> {
+		match name {
+			"mean" => Some(Alignment::Mean),
+			"weighted" => Some(Alignment::WeightedMean),
+			"median" => Some(Alignment::Median),
+			_ => None
+		}
+	}
+}
+
+/// How a boid turns and accelerates in response to what it sees
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Steering {
+	/// nudges the angular speed left or right, depending on which side has more of something
+	Angular,
+	/// textbook separation, alignment and cohesion forces on a velocity vector
+	Reynolds
+}
+
+impl Steering {
+	pub fn from_name(name: &str) -> Option<Steering> {
+		match name {
+			"angular" => Some(Steering::Angular),
+			"reynolds" => Some(Steering::Reynolds),
+			_ => None
+		}
+	}
+}
+
+/// Which boids count as neighbours
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Neighbourhood {
+	/// every boid within the vision cone
+	Metric,
+	/// the k nearest boids in the field of view, however far they are,
+	/// like starlings that keep track of about 7 flockmates
+	Topological(usize)
+}
+
+impl Neighbourhood {
+	/// "metric", or the number of nearest neighbours
+	pub fn from_name(name: &str) -> Option<Neighbourhood> {
+		match name {
+			"metric" => Some(Neighbourhood::Metric),
+			_ => name.parse().ok().filter(|k| *k > 0).map(Neighbourhood::Topological)
+		}
+	}
+}
+
+/// Weights and limits of `Steering::Reynolds`
+#[derive(Clone, Copy, Debug)]
+pub struct Forces {
+	pub separation: f64,
+	pub alignment: f64,
+	pub cohesion: f64,
+	pub avoid_walls: f64,
+	/// maximum length of each steering force, per frame
+	pub max_force: f64
+}
+
+/// Everything besides other boids that `Boid::update` reacts to
+pub struct Environment<'a> {
+	pub canvas: &'a Canvas,
+	pub settings: &'a Settings,
+	/// steering rules of `Steering::Angular`
+	pub rules: &'a Pipeline,
+	pub goals: &'a Goals,
+	pub flow: &'a Flow,
+	/// frames elapsed since the universe was created
+	pub time: f64,
+	/// set while the universe is profiled
+	pub counters: Option<&'a Counters>
+}
+
+/// Universe-wide parameters of `Boid::update`
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+	/// boids don't see neighbours hidden behind closer ones or behind obstacles
+	pub occlusion: bool,
+	pub neighbourhood: Neighbourhood,
+	pub alignment: Alignment,
+	pub steering: Steering,
+	pub forces: Forces
+}
+
+impl Default for Settings {
+	fn default() -> Settings {
+		Settings {
+			occlusion: false,
+			neighbourhood: Neighbourhood::Metric,
+			alignment: Alignment::WeightedMean,
+			steering: Steering::Angular,
+			forces: Forces {
+				separation: 1.5,
+				alignment: 1.0,
+				cohesion: 1.0,
+				avoid_walls: 2.0,
+				max_force: 0.05
+			}
+		}
+	}
 }
 
 #[wasm_bindgen]
@@ -18,317 +177,433 @@ extern "C" {
 
 	#[wasm_bindgen(js_namespace = console, js_name = log)]
 	fn log_f64_f64(a: f64, b: f64);
-
-	#[wasm_bindgen(js_namespace = Math)]
-	fn random() -> f64;
 }
 
-#[derive(Clone)]
-pub struct Point {
-	pub x: f64,
-	pub y: f64
-}
+/// Position on the canvas
+pub type Point = Vec2;
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug)]
 pub struct Cone {
 	pub radius: f64,
 	pub radians: f64
 }
 
-#[derive(Clone)]
-struct Speed {
-	min: f64,
-	max: f64,
-	value: f64
+/// Part of the field of view, split along the heading
+pub enum Side {
+	Both,
+	Left,
+	Right
 }
 
-#[derive(Clone)]
-pub struct Body {
-	size: f64,
-	width: f64,
-	pub color: String,
-	angle: f64
+/// What `Boid::update` changes about a boid, see `Flock::apply`
+#[derive(Clone, Copy, Debug)]
+pub struct Motion {
+	pub point: Point,
+	pub angle: f64,
+	pub angular_speed: f64,
+	pub linear_speed: f64,
+	pub drawing_angle: f64,
+	pub neighbours: usize,
+	pub waypoints_reached: usize
 }
 
-#[derive(Clone)]
-struct Behaviors {
-	avoid_obstacles: f64,
-	avoid_entity: f64,
-	follow_group: f64,
-	go_to_group: f64
+impl Motion {
+	fn update_drawing_angle(&mut self, frames: f64) {
+		let drawing_angle = Angle::new(self.drawing_angle);
+		let difference = Angle::new(self.angle).difference(drawing_angle);
+		let limit = PI * 2.0 / 45.0 * frames;
+		self.drawing_angle = (drawing_angle + difference.max(- limit).min(limit)).radians();
+	}
 }
 
-static mut ID_COUNT: u32 = 0;
-
-#[derive(Clone)]
-struct Angle {
-	value: f64
+/// Thin view over the boid at `index` of a `Flock`
+#[derive(Clone, Copy)]
+pub struct Boid<'a> {
+	flock: &'a Flock,
+	index: usize
 }
-impl Angle {
-	fn new(value: f64) -> Angle {
-		Angle { value: Angle::modulo(value) }
+
+impl<'a> Boid<'a> {
+	pub(crate) fn new(flock: &'a Flock, index: usize) -> Boid<'a> {
+		Boid { flock, index }
 	}
 
-	fn set(&mut self, new_value: f64) {
-		self.value = Angle::modulo(new_value);
+	/// Position of the boid in its `Flock`
+	pub fn index(&self) -> usize {
+		self.index
 	}
 
-	fn get(&self) -> f64 {
-		self.value
+	pub fn id(&self) -> u32 {
+		self.flock.id[self.index]
 	}
 
-	fn sin(&self) -> f64 {
-		self.value.sin()
+	pub fn x(&self) -> f64 {
+		self.flock.x[self.index]
 	}
 
-	fn cos(&self) -> f64 {
-		self.value.cos()
+	pub fn y(&self) -> f64 {
+		self.flock.y[self.index]
 	}
 
-	fn modulo(value: f64) -> f64 {
-		if value < 0.0 {
-			value % (PI * 2.0) + PI * 2.0
-		} else {
-			value % (PI * 2.0)
-		}
+	pub fn point(&self) -> Point {
+		Point::new(self.x(), self.y())
 	}
-}
-impl std::ops::AddAssign<f64> for Angle {
-	fn add_assign(&mut self, other: f64) {
-		self.value = Angle::modulo(self.value + other);
+
+	fn angle(&self) -> f64 {
+		self.flock.angle[self.index]
 	}
-}
 
-enum Side {
-	Both,
-	Left,
-	Right
-}
+	pub fn heading(&self) -> f64 {
+		self.angle()
+	}
 
-#[derive(Clone)]
-pub struct Boid {
-	pub id: u32,
-	pub point: Point,
-	pub vision: Cone,
-	angle: Angle,
-	weight: f64,
-	angular_speed: Speed,
-	linear_speed: Speed,
-	pub body: Body,
-	behaviors: Behaviors
-}
+	pub fn angular_speed(&self) -> f64 {
+		self.flock.angular_speed[self.index]
+	}
 
-impl Boid {
-	pub fn new() -> Boid {
-		let id: u32;
-		unsafe {
-			ID_COUNT += 1;
-			id = ID_COUNT;
-		}
-		Boid {
-			id,
-			point: Point {
-				x: 0.0, 
-				y: 0.0
-			},
-			vision: Cone {
-				radius: 0.0,
-				radians: 0.0
-			},
-			angle: Angle::new(0.0),
-			weight: 0.0,
-			angular_speed: Speed {
-				min: 0.0,
-				max: 0.0,
-				value: 0.0
-			},
-			linear_speed: Speed {
-				min: 0.0,
-				max: 0.0,
-				value: 0.0
-			},
-			body: Body {
-				size: 0.0,
-				width: 0.0,
-				color: String::new(),
-				angle: 0.0
-			},
-			behaviors: Behaviors {
-				avoid_obstacles: 2.0,
-				avoid_entity: 0.2,
-				follow_group: 0.07,
-				go_to_group: 0.02
-			}
-		}
+	pub fn speed(&self) -> f64 {
+		self.flock.linear_speed[self.index]
 	}
 
-	pub fn set_initial_weight(&mut self, weight_boost: f64) {
-		self.weight = 1.0 + weight_boost;
-		self.body.size = 10.0 + random().min(2.0) * 15.0;
+	pub fn weight(&self) -> f64 {
+		self.flock.weight[self.index]
 	}
 
-	pub fn set_initial_coordinates(&mut self, x: f64, y: f64) {
-		self.point.x = x;
-		self.point.y = y;
-		// log_f64_f64(x, y);
+	pub fn traits(&self) -> &'a Traits {
+		&self.flock.traits[self.index]
 	}
 
-	pub fn set_initial_angle(&mut self, angle: f64) {
-		self.angle.set(angle);
-		self.body.angle = self.angle.get();
+	pub fn vision(&self) -> &'a Cone {
+		&self.traits().vision
 	}
 
-	pub fn set_initial_vision(&mut self, radius: f64, radians: f64) {
-		self.vision.radius = radius;
-		self.vision.radians = radians;
+	pub fn size(&self) -> f64 {
+		self.traits().size
 	}
 
-	pub fn set_max_linear_speed(&mut self, speed: f64) {
-		self.linear_speed.min = 0.2;
-		self.linear_speed.value = self.linear_speed.min;
-		self.linear_speed.max = 2.0 + speed * 1.0;
-		self.body.width = 5.0 + (1.0 - speed).max(0.0) * 10.0;
+	pub fn width(&self) -> f64 {
+		self.traits().width
 	}
 
-	pub fn set_max_angular_speed(&mut self, speed: f64) {
-		self.angular_speed.min = 0.0;
-		self.angular_speed.value = 0.0;
-		self.angular_speed.max = PI * 2.0 / 45.0 * (speed + 1.0);
-		if self.body.color.is_empty() {
-			self.reset_color();
-		}
+	fn drawing_angle(&self) -> f64 {
+		self.flock.drawing_angle[self.index]
 	}
 
-	pub fn reset_color(&mut self) {
-		let speed = self.angular_speed.max * 45.0 / (PI * 2.0) - 1.0;
-		let purple = (speed * 180.0).round() as i32;
-		let green = ((1.0 - speed) * 180.0).round() as i32;
-		self.body.color = format!("rgb({},{},{})", purple, green, purple);
+	pub fn goal_seeking(&self) -> f64 {
+		self.traits().goal_seeking
 	}
 
-	pub fn set_all_initial(&mut self, canvas: &Canvas) {
-		if self.weight == 0.0 { self.set_initial_weight(random()); }
-		if self.point.x == 0.0 && self.point.y == 0.0 { self.set_initial_coordinates(random() * canvas.width, random() * canvas.height); }
-		if self.angle.get() == 0.0 { self.set_initial_angle(random() * PI * 2.0); }
-		if self.vision.radius == 0.0 || self.vision.radians == 0.0 { self.set_initial_vision(100.0, 1.25 * PI); }
-		if self.linear_speed.max == 0.0 { self.set_max_linear_speed(random()); }
-		if self.angular_speed.max == 0.0 { self.set_max_angular_speed(random()); }
+	pub fn waypoints_reached(&self) -> usize {
+		self.flock.waypoints_reached[self.index]
 	}
 
-	pub fn update(&mut self, canvas: &Canvas, boids: &Vec<&Boid>, frames: f64) {
-		// default update speeds
-		self.angular_speed.value *= (0.85_f64).powf(frames);
-		self.linear_speed.value += 0.03 * frames;
-		
-		// environment update speeds
-		let (sees_wall, wall_angle, wall_distance) = self.test_wall_visibility(canvas);
-		if sees_wall {
-			self.angular_speed.value += wall_angle.signum() / wall_distance * self.behaviors.avoid_obstacles * frames;
-			self.linear_speed.value -= 0.03 * wall_distance / self.vision.radius * frames;
+	/// Number of boids seen during the last update
+	pub fn neighbours(&self) -> usize {
+		self.flock.neighbours[self.index]
+	}
+
+	pub fn color(&self, mode: &ColorMode) -> Rgba {
+		match mode {
+			ColorMode::Species => self.traits().color,
+			ColorMode::Speed => {
+				let limits = &self.traits().linear_speed;
+				let ratio = (self.speed() - limits.min) / (limits.max - limits.min);
+				Rgba::rgb(40, 80, 220).lerp(Rgba::rgb(230, 40, 40), ratio)
+			},
+			ColorMode::Heading => Rgba::from_hue(self.drawing_angle()),
+			ColorMode::Neighbours => Rgba::rgb(30, 30, 60).lerp(Rgba::rgb(255, 230, 80), self.neighbours() as f64 / 20.0),
+			ColorMode::Palette => PALETTE[self.id() as usize % PALETTE.len()],
+			// boids don't know their cluster, `Universe::render` handles this one
+			ColorMode::Cluster => self.traits().color
+		}
+	}
+
+	/// The flockmates among `boids` that this boid reacts to, see `update`
+	pub(crate) fn visible<'b>(&self, environment: &Environment, boids: &[Boid<'b>]) -> Vec<Boid<'b>> {
+		let visible = match environment.settings.neighbourhood {
+			Neighbourhood::Metric => self.filter_points_by_visibility(boids, &Side::Both),
+			Neighbourhood::Topological(k) => self.filter_nearest(boids, k)
+		};
+		if environment.settings.occlusion {
+			self.filter_occluded(visible, environment.canvas)
+		} else {
+			visible
 		}
+	}
 
-		let visible_points = self.filter_points_by_visibility(boids, &Side::Both);
-		let (too_close, direction) = self.find_closest_direction(&visible_points);
-		if too_close {
-			self.angular_speed.value += direction * self.behaviors.avoid_entity * frames;
-			self.linear_speed.value -= 0.03 * frames;
+	/// With `Neighbourhood::Topological`, `boids` must include the k nearest boids in the field of view.
+	/// Only reads the flock, so that every boid reacts to the same state of the others,
+	/// and returns what to write back with `Flock::apply`
+	pub fn update(&self, environment: &Environment, boids: &[Boid], frames: f64) -> Motion {
+		let canvas = environment.canvas;
+		let visible_points = self.visible(environment, boids);
+		if let Some(counters) = environment.counters {
+			Counters::add(&counters.candidates, boids.len() as u64);
+			Counters::add(&counters.visible, visible_points.len() as u64);
+		}
+		let mut motion = Motion {
+			point: self.point(),
+			angle: self.angle(),
+			angular_speed: self.angular_speed(),
+			linear_speed: self.speed(),
+			drawing_angle: self.drawing_angle(),
+			neighbours: visible_points.len(),
+			waypoints_reached: self.waypoints_reached()
+		};
+
+		match environment.settings.steering {
+			Steering::Angular => self.steer_by_rules(&mut motion, environment, &visible_points, frames),
+			Steering::Reynolds => self.steer_by_forces(&mut motion, environment, &visible_points, frames)
 		}
 
-		let (sees_group, angle, count) = self.find_group_direction(&visible_points);
-		if sees_group && count > 4 {
-			self.angular_speed.value += angle.signum() * self.behaviors.follow_group * frames;
+		// ambient flow turns boids to face it
+		let flow = environment.flow;
+		let (flow_x, flow_y) = flow.field.at(self.x(), self.y(), environment.time, canvas);
+		if flow_x != 0.0 || flow_y != 0.0 {
+			let strength = (flow_x.powi(2) + flow_y.powi(2)).sqrt();
+			motion.angular_speed += turn_towards(self.angle(), flow_x, flow_y).signum() * strength * flow.heading_bias * frames;
 		}
 
-		let (sees_group, direction) = self.find_density_direction(&visible_points);
-		if sees_group {
-			self.angular_speed.value += direction * self.behaviors.go_to_group * frames;
+		let previous = self.point();
+
+		// default update positions
+		let angle = Angle::new(motion.angle) + motion.angular_speed * frames;
+		motion.angle = angle.radians();
+		motion.point.x -= angle.sin() * motion.linear_speed * frames;
+		motion.point.y -= angle.cos() * motion.linear_speed * frames;
+
+		// ambient flow carries boids along
+		motion.point.x += flow_x * flow.drift * frames;
+		motion.point.y += flow_y * flow.drift * frames;
+
+		// cap positions, in the middle of sides under twice the padding
+		let cap = |value: f64, length: f64| {
+			let padding = canvas.padding.min(length / 2.0);
+			value.max(padding).min(length - padding)
+		};
+		motion.point.x = cap(motion.point.x, canvas.width);
+		motion.point.y = cap(motion.point.y, canvas.height);
+		if canvas.has_obstacles() && canvas.is_open(previous.x, previous.y) {
+			// stop at the first wall crossed, not only when landing behind one
+			let heading = motion.point - previous;
+			if let Some(hit) = canvas.first_hit(&previous, &motion.point) {
+				// a hit right behind a boid leaving an obstacle's margin doesn't hold it back
+				if hit.normal.0 * heading.x + hit.normal.1 * heading.y < 0.0 {
+					motion.point = previous + heading.with_length((hit.distance - WALL_GAP).max(0.0));
+				}
+			}
+			if !canvas.is_open(motion.point.x, motion.point.y) {
+				motion.point = previous;
+			}
+		}
+
+		if let Some((x, y)) = environment.goals.waypoint(motion.waypoints_reached) {
+			if (Vec2::new(x, y) - motion.point).length_squared() < environment.goals.reach.powi(2) {
+				motion.waypoints_reached += 1;
+			}
+		}
+
+		motion.update_drawing_angle(frames);
+		motion
+	}
+
+	fn steer_by_rules(&self, motion: &mut Motion, environment: &Environment, visible_points: &[Boid], frames: f64) {
+		let traits = self.traits();
+
+		// default update speeds
+		motion.angular_speed *= (0.85_f64).powf(frames);
+		motion.linear_speed += 0.03 * frames;
+
+		for (rule, weight) in environment.rules.iter() {
+			let steer = rule.steer(self, visible_points, environment);
+			motion.angular_speed += steer.turn * weight * frames;
+			motion.linear_speed += steer.speed * frames;
 		}
 		
 		// cap speeds
-		self.angular_speed.value = self.angular_speed.value.signum() * self.angular_speed.value.abs().min(self.angular_speed.max).max(self.angular_speed.min);
-		self.linear_speed.value = self.linear_speed.value.min(self.linear_speed.max).max(self.angular_speed.min);
+		motion.angular_speed = motion.angular_speed.signum() * motion.angular_speed.abs().min(traits.angular_speed.max).max(traits.angular_speed.min);
+		motion.linear_speed = motion.linear_speed.min(traits.linear_speed.max).max(traits.angular_speed.min);
+	}
 
-		// default update positions
-		self.angle += self.angular_speed.value * frames;
-		self.point.x -= self.angle.sin() * self.linear_speed.value * frames;
-		self.point.y -= self.angle.cos() * self.linear_speed.value * frames;
+	/// Craig Reynolds' steering: each rule is a desired velocity,
+	/// and the force is the difference with the current velocity (capped to `max_force`).
+	/// The resulting velocity is turned back into a speed and an angular speed.
+	fn steer_by_forces(&self, motion: &mut Motion, environment: &Environment, visible_points: &[Boid], frames: f64) {
+		let canvas = environment.canvas;
+		let forces = &environment.settings.forces;
+		let max_speed = self.traits().linear_speed.max;
+		let velocity = Vec2::from_heading(Angle::new(self.angle())) * self.speed();
+		let steering = Steer { velocity, max_speed, forces };
+		let steer = |desired: Vec2, weight: f64| steering.towards(desired, weight);
+		let body = |boid: &Boid| Body {
+			position: boid.point(),
+			velocity: Vec2::from_heading(Angle::new(boid.angle())) * boid.speed(),
+			size: boid.size()
+		};
+		let mut acceleration = steering.flocking(&body(self), visible_points.iter().map(body));
+
+		// walls: back towards the inside when closer than half the vision range
+		if canvas.has_obstacles() {
+			if let Some(hit) = self.cast_walls(canvas) {
+				acceleration += steer(hit.normal.into(), forces.avoid_walls);
+			}
+		} else {
+			let margin = canvas.padding + self.vision().radius / 2.0;
+			let mut inward = Vec2::ZERO;
+			if self.x() < margin { inward.x += 1.0; }
+			if self.x() > canvas.width - margin { inward.x -= 1.0; }
+			if self.y() < margin { inward.y += 1.0; }
+			if self.y() > canvas.height - margin { inward.y -= 1.0; }
+			if inward != Vec2::ZERO {
+				acceleration += steer(inward, forces.avoid_walls);
+			}
+		}
 
-		// cap positions
-		self.point.x = self.point.x.max(canvas.padding).min(canvas.width - canvas.padding);
-		self.point.y = self.point.y.max(canvas.padding).min(canvas.height - canvas.padding);
+		// goals: seek attractors and the next waypoint, flee repellers
+		for attractor in &environment.goals.attractors {
+			let pull = attractor.pull(self.x(), self.y());
+			if pull != 0.0 {
+				let towards = Vec2::new(attractor.x, attractor.y) - self.point();
+				acceleration += steer(towards * pull.signum(), pull.abs() * self.goal_seeking());
+			}
+		}
+		if let Some((x, y)) = environment.goals.waypoint(self.waypoints_reached()) {
+			acceleration += steer(Vec2::new(x, y) - self.point(), self.goal_seeking());
+		}
+
+		let velocity = velocity + acceleration * frames;
+		let speed = velocity.length();
+		motion.linear_speed = speed.min(max_speed).max(self.traits().linear_speed.min);
+		if speed > 0.0 && frames > 0.0 {
+			motion.angular_speed = velocity.heading().difference(Angle::new(self.angle())) / frames;
+		}
+	}
 
-		self.update_drawing_angle(frames);
+	pub fn sees(&self, other: &Boid) -> bool {
+		self.index != other.index && self.test_point_visibility(&other.point(), &Side::Both)
+	}
+
+	/// Whether `other` is in the field of view, however far
+	pub fn faces(&self, other: &Boid) -> bool {
+		self.index != other.index && self.test_point_direction(&other.point(), &Side::Both)
+	}
+
+	/// `boids` within the vision cone, or its left or right half
+	pub fn filter_points_by_visibility<'b>(&self, boids: &[Boid<'b>], side: &Side) -> Vec<Boid<'b>> {
+		boids
+			.iter()
+			.filter(|boid| self.index != boid.index && self.test_point_visibility(&boid.point(), side))
+			.copied()
+			.collect()
 	}
 
-	fn filter_points_by_visibility<'a>(&self, boids: &Vec<&'a Boid>, side: &Side) -> Vec<&'a Boid> {
+	fn filter_points_by_direction<'b>(&self, boids: &[Boid<'b>], side: &Side) -> Vec<Boid<'b>> {
 		boids
-			.clone()
 			.iter()
-			.filter(|boid| self.id != boid.id && self.test_point_visibility(&boid.point, side))
-			.map(|&boid| boid)
+			.filter(|boid| self.index != boid.index && self.test_point_direction(&boid.point(), side))
+			.copied()
+			.collect()
+	}
+
+	/// The `k` boids closest to this one in its field of view, closest first
+	fn filter_nearest<'b>(&self, boids: &[Boid<'b>], k: usize) -> Vec<Boid<'b>> {
+		let squared_distance = |boid: &Boid| (boid.point() - self.point()).length_squared();
+		let mut nearest = self.filter_points_by_direction(boids, &Side::Both);
+		nearest.sort_by(|a, b| squared_distance(a).total_cmp(&squared_distance(b)));
+		nearest.truncate(k);
+		nearest
+	}
+
+	/// Nearest boids first, each one masks the slice of the field of view it covers,
+	/// so that boids further away in that slice are hidden. The slices are tracked in
+	/// angular bins, and only walls need a ray cast.
+	fn filter_occluded<'b>(&self, mut visible: Vec<Boid<'b>>, canvas: &Canvas) -> Vec<Boid<'b>> {
+		const BINS: usize = 128;
+		let bin_width = PI * 2.0 / BINS as f64;
+		let mut occupied = [false; BINS];
+
+		let squared_distance = |boid: &Boid| (boid.point() - self.point()).length_squared();
+		visible.sort_by(|a, b| squared_distance(a).total_cmp(&squared_distance(b)));
+
+		visible
+			.into_iter()
+			.filter(|boid| {
+				let offset = boid.point() - self.point();
+				let distance = offset.length();
+				let bearing = offset.heading().radians();
+				let bin = ((bearing / bin_width) as usize).min(BINS - 1);
+				if occupied[bin] || (canvas.has_obstacles() && canvas.blocks(&self.point(), &boid.point())) {
+					return false
+				}
+
+				// a body seen from a distance covers this half angle on each side of its bearing
+				let half_width = if distance > boid.size() / 2.0 {
+					(boid.size() / 2.0 / distance).asin()
+				} else {
+					PI
+				};
+				let first = ((bearing - half_width) / bin_width).round() as i64;
+				let last = ((bearing + half_width) / bin_width).round() as i64;
+				for covered in first..last {
+					occupied[covered.rem_euclid(BINS as i64) as usize] = true;
+				}
+				true
+			})
 			.collect()
 	}
 
 	fn test_point_visibility(&self, point: &Point, side: &Side) -> bool {
-		let dx = self.point.x - point.x;
-		let dy = self.point.y - point.y;
-	
-		let distance = dx.powi(2) + dy.powi(2);
-		if distance > self.vision.radius.powi(2) {
+		if (self.point() - *point).length_squared() > self.vision().radius.powi(2) {
 			return false
 		}
 	
-		let angle = angle_from_deltas(dx, dy);
-		let delta_angle = (PI * 2.0 - self.angle.get() + angle) % (PI * 2.0);
+		self.test_point_direction(point, side)
+	}
+
+	fn test_point_direction(&self, point: &Point, side: &Side) -> bool {
+		let bearing = (*point - self.point()).heading();
+		let delta_angle = (bearing - Angle::new(self.angle())).radians();
 	
 		match side {
-			Side::Both => delta_angle < self.vision.radians / 2.0 || delta_angle > PI * 2.0 - self.vision.radians / 2.0,
-			Side::Left => delta_angle < self.vision.radians / 2.0,
-			Side::Right => delta_angle > PI * 2.0 - self.vision.radians / 2.0
+			Side::Both => delta_angle < self.vision().radians / 2.0 || delta_angle > PI * 2.0 - self.vision().radians / 2.0,
+			Side::Left => delta_angle < self.vision().radians / 2.0,
+			Side::Right => delta_angle > PI * 2.0 - self.vision().radians / 2.0
 		}
 	}
 
 	/// Of the Boids too close, are there more on the Left or on the Right
 	/// return direction in which to turn to get away
-	fn find_closest_direction(&self, boids: &Vec<&Boid>) -> (bool, f64) {
-		let too_close: Vec<&Boid> = boids
+	pub(crate) fn find_closest_direction(&self, boids: &[Boid]) -> (bool, f64) {
+		let too_close = boids
 			.iter()
 			.filter(|boid| {
-				let distance = ((boid.point.x - self.point.x).powi(2) + (boid.point.y - self.point.y).powi(2)).sqrt();
-				distance < self.body.size + boid.body.size
-			})
-			.map(|&boid| boid)
-			.collect();
-		if too_close.len() == 0 {
-			return (false, 0.0)
+				boid.point().distance(self.point()) < self.size() + boid.size()
+			});
+		match self.weigh_sides(too_close) {
+			Some((left_weight, right_weight)) => (true, (right_weight - left_weight).signum()),
+			None => (false, 0.0)
 		}
-
-		let too_close_left: Vec<&Boid> = too_close.iter().filter(|boid| self.test_point_visibility(&boid.point, &Side::Left)).map(|&boid| boid).collect();
-		let too_close_right: Vec<&Boid> = too_close.iter().filter(|boid| self.test_point_visibility(&boid.point, &Side::Right)).map(|&boid| boid).collect();
-		if too_close_left.len() == 0 && too_close_right.len() == 0 {
-			return (false, 0.0)
-		}
-
-		let left_weight = too_close_left.iter().fold(0.0, |sum, x| sum + x.weight);
-		let right_weight = too_close_right.iter().fold(0.0, |sum, x| sum + x.weight);
-
-		(true, (right_weight - left_weight).signum())
 	}
 
 	/// Average angle of a vector of Boids
-	fn find_group_direction(&self, boids: &Vec<&Boid>) -> (bool, f64, usize) {
+	pub(crate) fn find_group_direction(&self, boids: &[Boid], alignment: &Alignment) -> (bool, f64, usize) {
 		let length = boids.len();
-		if boids.len() == 0 {
+		if boids.is_empty() {
 			return (false, 0.0, 0)
 		}
 
-		let total_weight = boids.iter().fold(0.0, |sum, x| sum + x.weight);
-		let atan2_x = boids.iter().fold(0.0, |sum, x| sum + x.angle.sin() * x.weight) / total_weight;
-		let atan2_y = boids.iter().fold(0.0, |sum, x| sum + x.angle.cos() * x.weight) / total_weight;
-		let angle_mean = atan2_x.atan2(atan2_y);
-
-		let lesser_diff = angle_mean - self.angle.get();
-		let greater_diff = angle_mean + PI * 2.0 - self.angle.get();
+		let angle_mean = match alignment {
+			Alignment::Mean => circular::mean(&boids.iter().map(|x| x.angle()).collect::<Vec<f64>>()),
+			Alignment::WeightedMean => circular::weighted_mean(&boids.iter().map(|x| (x.angle(), x.weight())).collect::<Vec<(f64, f64)>>()),
+			Alignment::Median => circular::median(&boids.iter().map(|x| x.angle()).collect::<Vec<f64>>())
+		};
+		let angle_mean = match angle_mean {
+			Some(angle) => angle,
+			None => return (false, 0.0, length)
+		};
+
+		let lesser_diff = angle_mean - self.angle();
+		let greater_diff = angle_mean + PI * 2.0 - self.angle();
 		let return_diff = if lesser_diff.abs() < greater_diff.abs() { lesser_diff } else { greater_diff };
 
 		(true, return_diff, length)
@@ -336,66 +611,88 @@ impl Boid {
 
 	/// Are there more Boids on the Left or on the Right 
 	/// return direction in which to turn to get closer
-	fn find_density_direction(&self, boids: &Vec<&Boid>) -> (bool, f64) {
-		let left_view = self.filter_points_by_visibility(boids, &Side::Left);
-		let right_view = self.filter_points_by_visibility(boids, &Side::Right);
-
-		if left_view.len() == 0 && right_view.len() == 0 {
-			return (false, 0.0)
+	pub(crate) fn find_density_direction(&self, boids: &[Boid]) -> (bool, f64) {
+		match self.weigh_sides(boids.iter()) {
+			Some((left_weight, right_weight)) => (true, (left_weight - right_weight).signum()),
+			None => (false, 0.0)
 		}
+	}
 
-		let left_weight = left_view.iter().fold(0.0, |sum, x| sum + x.weight);
-		let right_weight = right_view.iter().fold(0.0, |sum, x| sum + x.weight);
-
-		(true, (left_weight - right_weight).signum())
+	/// Total weight of the Boids on the Left and on the Right,
+	/// `None` if there are none on either side
+	fn weigh_sides<'b, 'c: 'b>(&self, boids: impl Iterator<Item = &'b Boid<'c>>) -> Option<(f64, f64)> {
+		let mut left_weight = 0.0;
+		let mut right_weight = 0.0;
+		let mut count = 0;
+		for boid in boids {
+			if self.index == boid.index {
+				continue
+			}
+			let point = boid.point();
+			if self.test_point_direction(&point, &Side::Left) {
+				left_weight += boid.weight();
+				count += 1;
+			} else if self.test_point_direction(&point, &Side::Right) {
+				right_weight += boid.weight();
+				count += 1;
+			}
+		}
+		if count == 0 {
+			None
+		} else {
+			Some((left_weight, right_weight))
+		}
 	}
 
-	fn test_wall_visibility(&self, canvas: &Canvas) -> (bool, f64, f64) {
-		let future_x = self.point.x - self.angle.sin() * self.vision.radius;
-		let future_y = self.point.y - self.angle.cos() * self.vision.radius;
+	pub(crate) fn test_wall_visibility(&self, canvas: &Canvas) -> (bool, f64, f64) {
+		if canvas.has_obstacles() {
+			return self.test_obstacle_visibility(canvas)
+		}
+		let future_x = self.x() - self.angle().sin() * self.vision().radius;
+		let future_y = self.y() - self.angle().cos() * self.vision().radius;
 		let mut returns: Vec<(f64, f64)> = vec![];
 		let mut count = 0;
 
 		if future_x < canvas.padding { // left
-			count = count + 1;
+			count += 1;
 			returns.push((
-				Angle::modulo(self.angle.get() / (PI / 2.0) - 1.0),
-				self.point.x - canvas.padding
+				Angle::modulo(self.angle() / (PI / 2.0) - 1.0),
+				self.x() - canvas.padding
 			));
 		}
 		if future_x > canvas.width - canvas.padding { // right
-			count = count + 1;
+			count += 1;
 			returns.push((
-				Angle::modulo(self.angle.get() / (PI / 2.0) - 3.0),
-				canvas.width - canvas.padding - self.point.x
+				Angle::modulo(self.angle() / (PI / 2.0) - 3.0),
+				canvas.width - canvas.padding - self.x()
 			));
 		}
 		if future_y < canvas.padding { // top
-			count = count + 1;
+			count += 1;
 			returns.push((
-				Angle::modulo(self.angle.get() / (PI / 2.0)),
-				self.point.y - canvas.padding
+				Angle::modulo(self.angle() / (PI / 2.0)),
+				self.y() - canvas.padding
 			));
 		}
 		if future_y > canvas.height - canvas.padding { // bottom
-			count = count + 1;
+			count += 1;
 			returns.push((
-				Angle::modulo(self.angle.get() / (PI / 2.0) - 2.0),
-				canvas.height - canvas.padding - self.point.y
+				Angle::modulo(self.angle() / (PI / 2.0) - 2.0),
+				canvas.height - canvas.padding - self.y()
 			));
 		}
 
 		if count == 0 {
-			return (false, 0.0, 0.0)
+			(false, 0.0, 0.0)
 		} else if count == 1 {
-			return (true, returns[0].0, returns[0].1)
+			(true, returns[0].0, returns[0].1)
 		} else {
 			// cheat
-			if self.point.x < canvas.padding * 10.0 && self.point.y < canvas.padding * 10.0 {
+			if self.x() < canvas.padding * 10.0 && self.y() < canvas.padding * 10.0 {
 				return (
 					true,
-					self.angular_speed.value,
-					(self.point.x - canvas.padding).abs().min((self.point.y - canvas.padding).abs())
+					self.angular_speed(),
+					(self.x() - canvas.padding).abs().min((self.y() - canvas.padding).abs())
 				)
 			}
 
@@ -403,114 +700,228 @@ impl Boid {
 				.sort_unstable_by(|a, b| if (a.1 - b.1).abs() > canvas.padding * 2.0 {
 					a.1.partial_cmp(&b.1).unwrap()
 				} else {
-					let a_angle_diff = absolute_angle_difference(a.0, self.angle.get());
-					let b_angle_diff = absolute_angle_difference(b.0, self.angle.get());
+					let a_angle_diff = Angle::new(a.0).difference(Angle::new(self.angle())).abs();
+					let b_angle_diff = Angle::new(b.0).difference(Angle::new(self.angle())).abs();
 					b_angle_diff.partial_cmp(&a_angle_diff).unwrap()
 				});
 
-			return (true, returns[0].0, returns[0].1)
+			(true, returns[0].0, returns[0].1)
 		}
 	}
 
-	pub fn update_drawing_angle(&mut self, frames: f64) {
-		if self.angle.get() - self.body.angle > PI {
-			self.body.angle += PI * 2.0;
-		} else if self.body.angle - self.angle.get() > PI {
-			self.body.angle -= PI * 2.0;
-		}
+	/// Look ahead for arena walls and mask obstacles, along the heading
+	/// and along both edges of the center of the field of view
+	fn cast_walls(&self, canvas: &Canvas) -> Option<Hit> {
+		[0.0, - self.vision().radians / 4.0, self.vision().radians / 4.0]
+			.iter()
+			.flat_map(|offset| {
+				let angle = self.angle() + offset;
+				let direction = (- angle.sin(), - angle.cos());
+				let arena = canvas.arena.as_ref().and_then(|arena| arena.cast(self.x(), self.y(), direction, self.vision().radius));
+				let mask = canvas.mask.as_ref().and_then(|mask| mask.cast(self.x(), self.y(), direction, self.vision().radius, canvas));
+				arena.into_iter().chain(mask)
+			})
+			.min_by(|a, b| a.distance.total_cmp(&b.distance))
+	}
 
-		let direction = (self.angle.get() - self.body.angle).signum();
-		let difference = (self.angle.get() - self.body.angle).abs();
-		let limit = PI * 2.0 / 45.0 * frames;
-		let capped_diff = if direction > 0.0 { difference.min(limit) } else { difference.max(limit) };
-		self.body.angle += direction * capped_diff;
-		self.body.angle %= PI * 2.0;
+	/// Same return as `test_wall_visibility`: the sign of the angle is the direction in which
+	/// to turn to follow the closest wall ahead
+	fn test_obstacle_visibility(&self, canvas: &Canvas) -> (bool, f64, f64) {
+		let hit = match self.cast_walls(canvas) {
+			Some(hit) => hit,
+			None => return (false, 0.0, 0.0)
+		};
+		let heading = (- self.angle().sin(), - self.angle().cos());
+		let along = if hit.tangent.0 * heading.0 + hit.tangent.1 * heading.1 >= 0.0 {
+			hit.tangent
+		} else {
+			(- hit.tangent.0, - hit.tangent.1)
+		};
+		let turn = turn_towards(self.angle(), along.0, along.1);
+		(true, if turn == 0.0 { 1.0 } else { turn }, hit.distance.max(1.0))
 	}
 
-	pub fn get_drawing_data(&self) -> ((f64, f64), (f64, f64), (f64, f64)) {
-		let draw_size = self.body.size * 0.9;
-		let draw_width = self.body.width * 1.0;
-		let center_x = self.body.angle.sin() * draw_size / 2.0;
-		let center_y = self.body.angle.cos() * draw_size / 2.0;
-		let hypotenuse = (draw_size.powi(2) + (draw_width / 2.0).powi(2)).sqrt();
+	/// Tip, rear right and rear left corners of the triangle drawn for the boid
+	pub fn get_drawing_data(&self) -> [Point; 3] {
+		let draw_size = self.size() * 0.9;
+		let draw_width = self.width() * 1.0;
+		let heading = Angle::new(self.drawing_angle());
+		let tip = self.point() + Vec2::from_heading(heading) * (draw_size / 2.0);
+		// the long sides go back from the tip, half the apex angle away from the heading
+		let back = - Vec2::from_heading(heading) * (draw_size.powi(2) + (draw_width / 2.0).powi(2)).sqrt();
 		let half_angle = (draw_width / draw_size / 2.0).asin();
-		(
-			(
-				self.point.x - center_x,
-				self.point.y - center_y
-			),
-			(
-				self.point.x + (PI / 2.0 - self.body.angle - half_angle).cos() * hypotenuse - center_x,
-				self.point.y + (PI / 2.0 - self.body.angle - half_angle).sin() * hypotenuse - center_y
-			),
-			(
-				self.point.x + (self.body.angle - half_angle).sin() * hypotenuse - center_x,
-				self.point.y + (self.body.angle - half_angle).cos() * hypotenuse - center_y
-			)
-		)
-	}
-
-	pub fn draw(&self, context: &web_sys::CanvasRenderingContext2d, with_field_of_view: bool) {
+		[tip, tip + back.rotate(half_angle), tip + back.rotate(- half_angle)]
+	}
+
+	pub fn draw(&self, context: &web_sys::CanvasRenderingContext2d, color: Rgba, with_field_of_view: bool) {
+		let css = color.to_css();
 		if with_field_of_view {
-			self.draw_field_of_view(context);
+			self.draw_field_of_view(context, &css);
 		}
-		let data = self.get_drawing_data();
-		context.set_fill_style(&JsValue::from_str(&self.body.color));
+		let [tip, rear_right, rear_left] = self.get_drawing_data();
+		context.set_fill_style_str(&css);
 		context.begin_path();
-		context.move_to((data.0).0, (data.0).1);
-		context.line_to((data.1).0, (data.1).1);
-		context.line_to((data.2).0, (data.2).1);
+		context.move_to(tip.x, tip.y);
+		context.line_to(rear_right.x, rear_right.y);
+		context.line_to(rear_left.x, rear_left.y);
 		context.fill();
-
 	}
 
-	fn draw_field_of_view(&self, context: &web_sys::CanvasRenderingContext2d) {
+	pub fn draw_field_of_view(&self, context: &web_sys::CanvasRenderingContext2d, css: &str) {
 		let alpha = context.global_alpha();
 		context.set_global_alpha(0.07);
-		context.set_fill_style(&JsValue::from_str(&self.body.color));
+		context.set_fill_style_str(css);
 		context.begin_path();
-		context.move_to(self.point.x, self.point.y);
+		context.move_to(self.x(), self.y());
 		let _ = context.arc_with_anticlockwise(
-			self.point.x,
-			self.point.y,
-			self.vision.radius,
-			- self.body.angle + self.vision.radians / 2.0 - PI / 2.0,
-			- self.body.angle - self.vision.radians / 2.0 - PI / 2.0,
+			self.x(),
+			self.y(),
+			self.vision().radius,
+			- self.drawing_angle() + self.vision().radians / 2.0 - PI / 2.0,
+			- self.drawing_angle() - self.vision().radians / 2.0 - PI / 2.0,
 			true
 		);
-		context.move_to(self.point.x, self.point.y);
+		context.move_to(self.x(), self.y());
 		context.fill();
 		context.set_global_alpha(alpha);
 	}
 
-	pub fn draw_connections(&self, context: &web_sys::CanvasRenderingContext2d, boids: &Vec<&Boid>) {
+	pub fn draw_connections(&self, context: &web_sys::CanvasRenderingContext2d, boids: &[Boid]) {
 		let visible = self.filter_points_by_visibility(boids, &Side::Both);
-		context.set_stroke_style(&JsValue::from_str("green"));
+		context.set_stroke_style_str("green");
 		visible.iter().for_each(|boid| {
 			context.begin_path();
-			context.move_to(self.point.x, self.point.y);
-			context.line_to(boid.point.x, boid.point.y);
+			context.move_to(self.x(), self.y());
+			context.line_to(boid.x(), boid.y());
 			context.stroke();
 		});
 	}
 }
 
-fn angle_from_deltas(dx: f64, dy: f64) -> f64 {
-	let unsigned_angle = (dx / dy).atan();
-	if dy < 0.0 {
-		unsigned_angle + PI
-	} else if dx < 0.0 {
-		unsigned_angle + PI * 2.0
-	} else {
-		unsigned_angle
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::rng::Rng;
+	use crate::universe::Universe;
+
+	/// Universe with only `count` boids, all on the same point
+	fn stacked(count: usize, steering: Steering) -> Universe {
+		let mut universe = Universe::new(None, 400.0, 300.0, 1);
+		universe.settings.steering = steering;
+		universe.flock = Flock::new();
+		let mut rng = Rng::new(2);
+		for _ in 0..count {
+			universe.flock.spawn(Some(Point::new(200.0, 150.0)), &universe.canvas, &mut rng);
+		}
+		universe
+	}
+
+	fn assert_finite(universe: &Universe) {
+		for boid in universe.flock.iter() {
+			assert!(boid.x().is_finite() && boid.y().is_finite(), "boid {} at {:?}", boid.id(), boid.point());
+			assert!(boid.heading().is_finite() && boid.speed().is_finite(), "boid {} heading {}", boid.id(), boid.heading());
+			assert!(boid.get_drawing_data().iter().all(|vertex| vertex.x.is_finite() && vertex.y.is_finite()));
+		}
+	}
+
+	#[test]
+	fn coincident_boids_stay_finite_with_angular_steering() {
+		let mut universe = stacked(3, Steering::Angular);
+		for _ in 0..100 {
+			universe.tick(1.0, false);
+			assert_finite(&universe);
+		}
+	}
+
+	#[test]
+	fn coincident_boids_stay_finite_with_reynolds_steering() {
+		let mut universe = stacked(3, Steering::Reynolds);
+		for _ in 0..100 {
+			universe.tick(1.0, false);
+			assert_finite(&universe);
+		}
+	}
+
+	#[test]
+	fn boid_does_not_see_itself() {
+		let universe = stacked(1, Steering::Angular);
+		let boid = universe.flock.get(0);
+		assert!(!boid.sees(&boid));
+		assert!(!boid.faces(&boid));
+	}
+
+	/// Ids of the boids that boid 0 sees through `filter_occluded`, out of all the others
+	fn unoccluded(universe: &Universe) -> Vec<u32> {
+		let observer = universe.flock.get(0);
+		let others: Vec<Boid> = universe.flock.iter().skip(1).collect();
+		let mut ids: Vec<u32> = observer.filter_occluded(others, &universe.canvas).iter().map(|boid| boid.id()).collect();
+		ids.sort_unstable();
+		ids
+	}
+
+	#[test]
+	fn closer_boids_hide_the_ones_behind_them() {
+		let mut universe = stacked(4, Steering::Angular);
+		let ids: Vec<u32> = universe.flock.id.clone();
+		// observer, then two boids further along the same line, then one aside
+		for (index, (x, y)) in [(100.0, 150.0), (130.0, 150.0), (250.0, 150.0), (100.0, 60.0)].iter().enumerate() {
+			universe.flock.x[index] = *x;
+			universe.flock.y[index] = *y;
+		}
+		assert_eq!(unoccluded(&universe), vec![ids[1], ids[3]]);
+		// out of line, the far one shows again
+		universe.flock.y[2] = 20.0;
+		assert_eq!(unoccluded(&universe), vec![ids[1], ids[2], ids[3]]);
 	}
-}
 
-fn absolute_angle_difference(alpha: f64, beta: f64) -> f64 {
-	let phi = (beta - alpha).abs() % (PI * 2.0);
-	if phi > PI {
-		PI * 2.0 - phi
-	} else {
-		phi
+	#[test]
+	fn nearest_boids_come_closest_first() {
+		let mut universe = stacked(4, Steering::Angular);
+		let ids: Vec<u32> = universe.flock.id.clone();
+		for (index, (x, y)) in [(200.0, 150.0), (200.0, 100.0), (230.0, 150.0), (200.0, 140.0)].iter().enumerate() {
+			universe.flock.x[index] = *x;
+			universe.flock.y[index] = *y;
+		}
+		// heading up
+		universe.flock.angle[0] = 0.0;
+		let boids: Vec<Boid> = universe.flock.iter().collect();
+		let nearest = |k: usize| -> Vec<u32> { boids[0].filter_nearest(&boids, k).iter().map(|boid| boid.id()).collect() };
+		assert_eq!(nearest(2), vec![ids[3], ids[2]]);
+		// more than there are boids in view
+		assert_eq!(nearest(10), vec![ids[3], ids[2], ids[1]]);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn walls_hide_boids() {
+		use crate::arena::Polygon;
+		let mut universe = stacked(3, Steering::Angular);
+		let ids: Vec<u32> = universe.flock.id.clone();
+		let room = Polygon::new(vec![(10.0, 10.0), (390.0, 10.0), (390.0, 290.0), (10.0, 290.0)]);
+		let wall = Polygon::new(vec![(150.0, 100.0), (152.0, 100.0), (152.0, 200.0), (150.0, 200.0)]);
+		universe.set_arena(Some(Arena::new(vec![room, wall])));
+		for (index, (x, y)) in [(100.0, 150.0), (200.0, 150.0), (100.0, 250.0)].iter().enumerate() {
+			universe.flock.x[index] = *x;
+			universe.flock.y[index] = *y;
+		}
+		assert_eq!(unoccluded(&universe), vec![ids[2]]);
+		universe.set_arena(None);
+		assert_eq!(unoccluded(&universe), vec![ids[1], ids[2]]);
+	}
+
+	#[test]
+	fn fast_boids_do_not_cross_thin_walls() {
+		use crate::arena::Polygon;
+		let mut universe = stacked(1, Steering::Angular);
+		let wall = Polygon::new(vec![(250.0, 20.0), (251.0, 20.0), (251.0, 280.0), (250.0, 280.0)]);
+		let room = Polygon::new(vec![(10.0, 10.0), (390.0, 10.0), (390.0, 290.0), (10.0, 290.0)]);
+		universe.set_arena(Some(Arena::new(vec![room, wall])));
+		universe.flock.x[0] = 249.0;
+		// heading right
+		universe.flock.angle[0] = - PI / 2.0;
+		universe.flock.linear_speed[0] = universe.flock.traits[0].linear_speed.max;
+		// one step is longer than the wall is thick
+		universe.tick(1.0, false);
+		assert!(universe.flock.x[0] < 250.0, "boid went through the wall to {}", universe.flock.x[0]);
+		assert!(universe.canvas.is_open(universe.flock.x[0], universe.flock.y[0]));
+	}
+}
@@ -0,0 +1,374 @@
+//! Headings, in radians modulo 2π
+
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+
+/// An angle in [0, 2π). Arithmetic wraps around, and comparisons are on the wrapped value,
+/// so just below 2π is greater than just above 0.
+///
+/// As a heading, it follows the convention of the boids: a boid heading at `a` moves along
+/// (-sin a, -cos a), so that a positive change turns it left on the canvas
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Angle {
+	value: f64
+}
+
+impl Angle {
+	pub const ZERO: Angle = Angle { value: 0.0 };
+
+	pub fn new(radians: f64) -> Angle {
+		Angle { value: Angle::modulo(radians) }
+	}
+
+	/// Heading of a boid moving along (dx, dy). The zero vector gives π
+	pub fn from_vector(dx: f64, dy: f64) -> Angle {
+		Angle::new((- dx).atan2(- dy))
+	}
+
+	/// Unit vector along which a boid with this heading moves
+	pub fn to_vector(&self) -> (f64, f64) {
+		(- self.value.sin(), - self.value.cos())
+	}
+
+	/// In [0, 2π)
+	pub fn radians(&self) -> f64 {
+		self.value
+	}
+
+	pub fn sin(&self) -> f64 {
+		self.value.sin()
+	}
+
+	pub fn cos(&self) -> f64 {
+		self.value.cos()
+	}
+
+	/// Signed shortest rotation from `other` to `self`, in (-π, π]
+	pub fn difference(&self, other: Angle) -> f64 {
+		let difference = (self.value - other.value).rem_euclid(PI * 2.0);
+		if difference > PI {
+			difference - PI * 2.0
+		} else {
+			difference
+		}
+	}
+
+	/// From `self` at 0 to `other` at 1, along the shortest arc
+	pub fn lerp(&self, other: Angle, t: f64) -> Angle {
+		*self + other.difference(*self) * t
+	}
+
+	/// `value` wrapped to [0, 2π)
+	pub fn modulo(value: f64) -> f64 {
+		let modulated = value.rem_euclid(PI * 2.0);
+		// tiny negative values round up to 2π
+		if modulated < PI * 2.0 {
+			modulated
+		} else {
+			0.0
+		}
+	}
+}
+
+impl std::ops::Add<f64> for Angle {
+	type Output = Angle;
+	fn add(self, other: f64) -> Self::Output {
+		Angle::new(self.value + other)
+	}
+}
+impl std::ops::Add<Angle> for f64 {
+	type Output = Angle;
+	fn add(self, other: Angle) -> Self::Output {
+		Angle::new(self + other.value)
+	}
+}
+impl std::ops::Add<Angle> for Angle {
+	type Output = Angle;
+	fn add(self, other: Angle) -> Self::Output {
+		Angle::new(self.value + other.value)
+	}
+}
+impl std::ops::AddAssign<f64> for Angle {
+	fn add_assign(&mut self, other: f64) {
+		self.value = Angle::modulo(self.value + other);
+	}
+}
+
+impl std::ops::Sub<f64> for Angle {
+	type Output = Angle;
+	fn sub(self, other: f64) -> Self::Output {
+		Angle::new(self.value - other)
+	}
+}
+impl std::ops::Sub<Angle> for f64 {
+	type Output = Angle;
+	fn sub(self, other: Angle) -> Self::Output {
+		Angle::new(self - other.value)
+	}
+}
+/// Counterclockwise rotation from `other` to `self`, see `Angle::difference` for the shortest one
+impl std::ops::Sub<Angle> for Angle {
+	type Output = Angle;
+	fn sub(self, other: Angle) -> Self::Output {
+		Angle::new(self.value - other.value)
+	}
+}
+impl std::ops::SubAssign<f64> for Angle {
+	fn sub_assign(&mut self, other: f64) {
+		self.value = Angle::modulo(self.value - other);
+	}
+}
+
+/// How many times `other` fits in `self`, e.g. the quadrant of an angle with
+/// `(angle / Angle::new(PI / 2.0)).floor()`
+impl std::ops::Div<Angle> for Angle {
+	type Output = f64;
+	fn div(self, other: Angle) -> Self::Output {
+		self.value / other.value
+	}
+}
+impl std::ops::Div<f64> for Angle {
+	type Output = Angle;
+	fn div(self, other: f64) -> Self::Output {
+		Angle::new(self.value / other)
+	}
+}
+impl std::ops::Mul<f64> for Angle {
+	type Output = Angle;
+	fn mul(self, other: f64) -> Self::Output {
+		Angle::new(self.value * other)
+	}
+}
+impl std::ops::Mul<Angle> for f64 {
+	type Output = Angle;
+	fn mul(self, other: Angle) -> Self::Output {
+		Angle::new(self * other.value)
+	}
+}
+
+impl std::ops::Neg for Angle {
+	type Output = Angle;
+	fn neg(self) -> Self::Output {
+		Angle::new(- self.value)
+	}
+}
+
+impl PartialEq<f64> for Angle {
+	fn eq(&self, other: &f64) -> bool {
+		self.value == Angle::modulo(*other)
+	}
+}
+impl PartialEq<Angle> for f64 {
+	fn eq(&self, other: &Angle) -> bool {
+		Angle::modulo(*self) == other.value
+	}
+}
+impl PartialEq<Angle> for Angle {
+	fn eq(&self, other: &Angle) -> bool {
+		self.value == other.value
+	}
+}
+impl PartialOrd<f64> for Angle {
+	fn partial_cmp(&self, other: &f64) -> Option<Ordering> {
+		self.value.partial_cmp(&Angle::modulo(*other))
+	}
+}
+impl PartialOrd<Angle> for Angle {
+	fn partial_cmp(&self, other: &Angle) -> Option<Ordering> {
+		self.value.partial_cmp(&other.value)
+	}
+}
+
+impl From<Angle> for f64 {
+	fn from(angle: Angle) -> f64 {
+		angle.value
+	}
+}
+
+impl std::fmt::Display for Angle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	const TAU: f64 = PI * 2.0;
+
+	fn assert_close(actual: f64, expected: f64) {
+		assert!((actual - expected).abs() < 1e-9, "expected {} got {}", expected, actual);
+	}
+
+	/// Distance on the circle, whatever the wrapping of either side
+	fn assert_same_direction(actual: Angle, expected: f64) {
+		assert!(actual.difference(Angle::new(expected)).abs() < 1e-9, "expected {} got {}", expected, actual);
+	}
+
+	fn any_angle() -> impl Strategy<Value = f64> {
+		-1000.0..1000.0
+	}
+
+	/// Within `margin` of the 0/2π seam, on either side
+	fn near_seam(margin: f64) -> impl Strategy<Value = f64> {
+		prop_oneof![0.0..margin, (TAU - margin)..TAU, -margin..0.0]
+	}
+
+	#[test]
+	fn new_wraps_exact_turns_to_zero() {
+		assert_eq!(Angle::new(TAU), Angle::ZERO);
+		assert_eq!(Angle::new(- TAU), Angle::ZERO);
+		assert_eq!(Angle::new(-1e-20).radians(), 0.0);
+		assert_eq!(Angle::new(-0.0).radians(), 0.0);
+	}
+
+	#[test]
+	fn difference_across_seam_is_short() {
+		assert_close(Angle::new(0.1).difference(Angle::new(TAU - 0.1)), 0.2);
+		assert_close(Angle::new(TAU - 0.1).difference(Angle::new(0.1)), -0.2);
+		assert_close(Angle::new(PI).difference(Angle::ZERO), PI);
+	}
+
+	#[test]
+	fn lerp_across_seam_goes_through_zero() {
+		assert_same_direction(Angle::new(TAU - 0.2).lerp(Angle::new(0.2), 0.5), 0.0);
+		assert_same_direction(Angle::new(TAU - 0.2).lerp(Angle::new(0.2), 0.25), -0.1);
+	}
+
+	#[test]
+	fn vectors_follow_the_boid_heading_convention() {
+		// heading 0 moves up the canvas, π/2 moves left
+		let (dx, dy) = Angle::ZERO.to_vector();
+		assert_close(dx, 0.0);
+		assert_close(dy, -1.0);
+		let (dx, dy) = Angle::new(PI / 2.0).to_vector();
+		assert_close(dx, -1.0);
+		assert_close(dy, 0.0);
+		assert_close(Angle::from_vector(1.0, 0.0).radians(), PI * 1.5);
+	}
+
+	#[test]
+	fn quadrants_by_division() {
+		let quarter = Angle::new(PI / 2.0);
+		assert_eq!((Angle::new(0.1) / quarter).floor(), 0.0);
+		assert_eq!((Angle::new(PI + 0.1) / quarter).floor(), 2.0);
+		assert_eq!((Angle::new(-0.1) / quarter).floor(), 3.0);
+	}
+
+	proptest! {
+		#[test]
+		fn new_is_in_range(radians in any_angle()) {
+			let angle = Angle::new(radians);
+			prop_assert!(angle.radians() >= 0.0 && angle.radians() < TAU);
+		}
+
+		#[test]
+		fn new_ignores_whole_turns(radians in any_angle(), turns in -50i32..50) {
+			assert_same_direction(Angle::new(radians + turns as f64 * TAU), radians);
+		}
+
+		#[test]
+		fn difference_is_shortest_and_signed(a in any_angle(), b in any_angle()) {
+			let (a, b) = (Angle::new(a), Angle::new(b));
+			let difference = a.difference(b);
+			prop_assert!(difference > -PI && difference <= PI);
+			assert_same_direction(b + difference, a.radians());
+		}
+
+		#[test]
+		fn difference_is_antisymmetric(a in any_angle(), b in any_angle()) {
+			let (a, b) = (Angle::new(a), Angle::new(b));
+			prop_assume!((a.difference(b).abs() - PI).abs() > 1e-9);
+			assert_close(a.difference(b), - b.difference(a));
+		}
+
+		#[test]
+		fn difference_matches_sub(a in any_angle(), b in any_angle()) {
+			let (a, b) = (Angle::new(a), Angle::new(b));
+			assert_same_direction(a - b, a.difference(b));
+		}
+
+		#[test]
+		fn lerp_reaches_both_ends(a in any_angle(), b in any_angle()) {
+			let (a, b) = (Angle::new(a), Angle::new(b));
+			assert_same_direction(a.lerp(b, 0.0), a.radians());
+			assert_same_direction(a.lerp(b, 1.0), b.radians());
+		}
+
+		#[test]
+		fn lerp_never_goes_the_long_way(a in any_angle(), b in any_angle(), t in 0.0..1.0) {
+			let (a, b) = (Angle::new(a), Angle::new(b));
+			let between = a.lerp(b, t);
+			assert_close(between.difference(a).abs() + b.difference(between).abs(), b.difference(a).abs());
+		}
+
+		#[test]
+		fn vectors_round_trip(radians in any_angle(), length in 0.001..1000.0) {
+			let angle = Angle::new(radians);
+			let (dx, dy) = angle.to_vector();
+			prop_assert!((dx.powi(2) + dy.powi(2) - 1.0).abs() < 1e-12);
+			assert_same_direction(Angle::from_vector(dx * length, dy * length), radians);
+		}
+
+		#[test]
+		fn div_angle_undoes_mul(a in 0.0..TAU, b in 0.001..TAU) {
+			let (a, b) = (Angle::new(a), Angle::new(b));
+			let ratio = a / b;
+			assert_close(ratio * b.radians(), a.radians());
+			prop_assert!(ratio >= 0.0);
+		}
+
+		#[test]
+		fn div_angle_by_itself_is_one(a in 0.001..TAU) {
+			let a = Angle::new(a);
+			prop_assert_eq!(a / a, 1.0);
+		}
+
+		#[test]
+		fn div_angle_near_seam_is_near_a_full_turn(a in near_seam(1e-6)) {
+			// just below 0 wraps to just below 2π, a whole turn of quadrants
+			let quadrants = Angle::new(a) / Angle::new(PI / 2.0);
+			prop_assert!(!(4e-6 * 2.0 / PI..=4.0 - 4e-6 * 2.0 / PI).contains(&quadrants));
+			prop_assert!(quadrants < 4.0);
+		}
+
+		#[test]
+		fn div_f64_wraps(a in any_angle(), divisor in 0.01..100.0f64) {
+			let angle = Angle::new(a) / divisor;
+			prop_assert!(angle.radians() >= 0.0 && angle.radians() < TAU);
+			assert_same_direction(angle, Angle::new(a).radians() / divisor);
+		}
+
+		#[test]
+		fn order_is_the_order_of_wrapped_values(a in any_angle(), b in any_angle()) {
+			let (a, b) = (Angle::new(a), Angle::new(b));
+			prop_assert_eq!(a.partial_cmp(&b), a.radians().partial_cmp(&b.radians()));
+			prop_assert_eq!(a.partial_cmp(&b.radians()), a.partial_cmp(&b));
+		}
+
+		#[test]
+		fn below_seam_is_greater_than_above(below in 1e-9..0.5f64, above in 0.0..0.5f64) {
+			// just below 0 wraps to just below 2π
+			prop_assert!(Angle::new(- below) > Angle::new(above));
+			prop_assert!(Angle::new(TAU - below) > Angle::new(above));
+			prop_assert!(Angle::new(above) < - below);
+		}
+
+		#[test]
+		fn order_with_f64_wraps_the_f64(a in 0.0..TAU, b in any_angle()) {
+			let angle = Angle::new(a);
+			prop_assert_eq!(angle.partial_cmp(&b), angle.partial_cmp(&Angle::new(b)));
+			prop_assert!(angle.partial_cmp(&b).is_some());
+		}
+
+		#[test]
+		fn equality_wraps_the_f64(a in 0.0..TAU) {
+			let angle = Angle::new(a);
+			prop_assert!(angle == a);
+			prop_assert!(a == angle);
+			prop_assert!(angle == Angle::new(a));
+		}
+	}
+}
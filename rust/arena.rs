@@ -0,0 +1,163 @@
+use std::f64::consts::PI;
+
+/// Closed polygon, the last point connects back to the first
+#[derive(Clone, Debug)]
+pub struct Polygon {
+	pub points: Vec<(f64, f64)>
+}
+
+impl Polygon {
+	pub fn new(points: Vec<(f64, f64)>) -> Polygon {
+		Polygon { points }
+	}
+
+	/// Regular polygon approximating a circle
+	pub fn circle(x: f64, y: f64, radius: f64, sides: usize) -> Polygon {
+		let points = (0..sides)
+			.map(|i| {
+				let angle = i as f64 / sides as f64 * PI * 2.0;
+				(x + angle.cos() * radius, y + angle.sin() * radius)
+			})
+			.collect();
+		Polygon { points }
+	}
+
+	pub fn segments(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+		self.points
+			.iter()
+			.copied()
+			.zip(self.points.iter().copied().cycle().skip(1))
+	}
+}
+
+/// Closest wall hit by a ray
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+	pub distance: f64,
+	/// unit vector along the wall
+	pub tangent: (f64, f64),
+	/// unit vector perpendicular to the wall, on the side the ray came from
+	pub normal: (f64, f64)
+}
+
+/// Space enclosed by any number of polygons, using the even-odd rule:
+/// a polygon inside another one is a hole, an island inside a hole is solid again, etc.
+#[derive(Clone, Debug)]
+pub struct Arena {
+	pub polygons: Vec<Polygon>
+}
+
+impl Arena {
+	pub fn new(polygons: Vec<Polygon>) -> Arena {
+		Arena { polygons }
+	}
+
+	pub fn contains(&self, x: f64, y: f64) -> bool {
+		let mut inside = false;
+		for ((ax, ay), (bx, by)) in self.polygons.iter().flat_map(|polygon| polygon.segments()) {
+			if (ay > y) != (by > y) && x < (bx - ax) * (y - ay) / (by - ay) + ax {
+				inside = !inside;
+			}
+		}
+		inside
+	}
+
+	/// Closest wall crossed by the ray from (x, y) along the unit vector `direction`, within `length`
+	pub fn cast(&self, x: f64, y: f64, direction: (f64, f64), length: f64) -> Option<Hit> {
+		let mut closest: Option<Hit> = None;
+		for ((ax, ay), (bx, by)) in self.polygons.iter().flat_map(|polygon| polygon.segments()) {
+			let segment = (bx - ax, by - ay);
+			let denominator = cross(direction, segment);
+			if denominator == 0.0 {
+				continue
+			}
+			let offset = (ax - x, ay - y);
+			let distance = cross(offset, segment) / denominator;
+			let along = cross(offset, direction) / denominator;
+			if distance < 0.0 || distance > length || !(0.0..=1.0).contains(&along) {
+				continue
+			}
+			if closest.is_some_and(|hit| hit.distance <= distance) {
+				continue
+			}
+			let norm = (segment.0.powi(2) + segment.1.powi(2)).sqrt();
+			let tangent = (segment.0 / norm, segment.1 / norm);
+			let normal = if cross(direction, tangent) > 0.0 {
+				(- tangent.1, tangent.0)
+			} else {
+				(tangent.1, - tangent.0)
+			};
+			closest = Some(Hit { distance, tangent, normal });
+		}
+		closest
+	}
+
+	pub fn draw(&self, context: &web_sys::CanvasRenderingContext2d) {
+		context.set_stroke_style_str("black");
+		context.begin_path();
+		for polygon in &self.polygons {
+			for (i, (x, y)) in polygon.points.iter().enumerate() {
+				if i == 0 {
+					context.move_to(*x, *y);
+				} else {
+					context.line_to(*x, *y);
+				}
+			}
+			context.close_path();
+		}
+		context.stroke();
+	}
+}
+
+fn cross(a: (f64, f64), b: (f64, f64)) -> f64 {
+	a.0 * b.1 - a.1 * b.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn square(x: f64, y: f64, size: f64) -> Polygon {
+		Polygon::new(vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)])
+	}
+
+	fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+		(a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+	}
+
+	#[test]
+	fn holes_and_islands_alternate() {
+		let arena = Arena::new(vec![square(0.0, 0.0, 100.0), square(25.0, 25.0, 50.0), square(45.0, 45.0, 10.0)]);
+		assert!(arena.contains(10.0, 10.0));
+		assert!(!arena.contains(30.0, 30.0));
+		assert!(arena.contains(50.0, 50.0));
+		assert!(!arena.contains(150.0, 50.0));
+		assert!(!arena.contains(- 1.0, 50.0));
+	}
+
+	#[test]
+	fn cast_finds_the_closest_wall_facing_the_ray() {
+		let arena = Arena::new(vec![square(0.0, 0.0, 100.0), square(60.0, 40.0, 20.0)]);
+		// towards the pillar, whose left side is closer than the outer wall
+		let hit = arena.cast(10.0, 50.0, (1.0, 0.0), 200.0).unwrap();
+		assert!((hit.distance - 50.0).abs() < 1e-9);
+		assert!(close(hit.normal, (- 1.0, 0.0)));
+		assert!(close((hit.tangent.0.abs(), hit.tangent.1.abs()), (0.0, 1.0)));
+		// away from it, the outer wall
+		let hit = arena.cast(10.0, 50.0, (- 1.0, 0.0), 200.0).unwrap();
+		assert!((hit.distance - 10.0).abs() < 1e-9);
+		assert!(close(hit.normal, (1.0, 0.0)));
+		// diagonally into the bottom wall
+		let diagonal = (0.5f64.sqrt(), 0.5f64.sqrt());
+		let hit = arena.cast(10.0, 80.0, diagonal, 200.0).unwrap();
+		assert!((hit.distance - 20.0 * 2.0f64.sqrt()).abs() < 1e-9);
+		assert!(close(hit.normal, (0.0, - 1.0)));
+	}
+
+	#[test]
+	fn cast_stops_at_its_length() {
+		let arena = Arena::new(vec![square(0.0, 0.0, 100.0)]);
+		assert!(arena.cast(50.0, 50.0, (0.0, 1.0), 49.0).is_none());
+		assert!(arena.cast(50.0, 50.0, (0.0, 1.0), 50.0).is_some());
+	}
+}
@@ -0,0 +1,87 @@
+//! Criterion benchmarks of a tick and of its spatial partitioning, with fixed seeds so that
+//! runs on different commits simulate the same flocks
+//!
+//! ```
+//! cargo bench [--features parallel] --bench flock [-- filter]
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use boids::universe::boid::{Boid, Side};
+use boids::universe::{grid_split, Universe};
+
+const SEED: u64 = 0;
+const SIZES: [usize; 4] = [1_000, 5_000, 20_000, 50_000];
+/// Boids per 1920x1080 area: the default flock of 200, and one four times as packed
+const DENSITIES: [(&str, f64); 2] = [("sparse", 200.0), ("dense", 800.0)];
+
+/// `count` boids at `density`, ticked a few times so that they have started gathering
+fn universe(count: usize, density: f64) -> Universe {
+	let scale = (count as f64 / density).sqrt();
+	let mut universe = Universe::new(None, 1920.0 * scale, 1080.0 * scale, SEED);
+	universe.add_boids(count.saturating_sub(universe.flock.len()));
+	for _ in 0..10 {
+		universe.tick(1.0, false);
+	}
+	universe
+}
+
+fn tick(c: &mut Criterion) {
+	let mut group = c.benchmark_group("tick");
+	group.sample_size(10);
+	for &(name, density) in &DENSITIES {
+		for &count in &SIZES {
+			let mut universe = universe(count, density);
+			let flock = universe.flock.clone();
+			group.throughput(Throughput::Elements(count as u64));
+			// every sample ticks the same flock, not one that keeps gathering as the bench runs.
+			// The flock ticked last is dropped with the inputs, outside of the timing
+			group.bench_with_input(BenchmarkId::new(name, count), &count, |b, _| b.iter_batched_ref(
+				|| flock.clone(),
+				|flock| {
+					std::mem::swap(&mut universe.flock, flock);
+					universe.tick(1.0, false);
+				},
+				BatchSize::LargeInput
+			));
+		}
+	}
+	group.finish();
+}
+
+fn grid(c: &mut Criterion) {
+	let mut group = c.benchmark_group("grid_split");
+	for &(name, density) in &DENSITIES {
+		for &count in &SIZES {
+			let universe = universe(count, density);
+			let boids: Vec<Boid> = universe.flock.iter().collect();
+			group.throughput(Throughput::Elements(count as u64));
+			group.bench_with_input(BenchmarkId::new(name, count), &count, |b, _| b.iter(|| grid_split(&universe.canvas, &boids)));
+		}
+	}
+	group.finish();
+}
+
+/// Every boid against the candidates of its cell, as in a tick
+fn visibility(c: &mut Criterion) {
+	let mut group = c.benchmark_group("filter_points_by_visibility");
+	for &(name, density) in &DENSITIES {
+		let count = 5_000;
+		let universe = universe(count, density);
+		let boids: Vec<Boid> = universe.flock.iter().collect();
+		let (map, cells) = grid_split(&universe.canvas, &boids);
+		group.throughput(Throughput::Elements(count as u64));
+		group.bench_function(BenchmarkId::new(name, count), |b| b.iter(|| {
+			boids
+				.iter()
+				.map(|boid| {
+					let (column, row) = map[boid.index()];
+					boid.filter_points_by_visibility(cells.get(column, row), &Side::Both).len()
+				})
+				.sum::<usize>()
+		}));
+	}
+	group.finish();
+}
+
+criterion_group!(benches, tick, grid, visibility);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+//! Runs a universe without a canvas and prints its metrics as CSV, one line per tick
+//!
+//! ```
+//! cargo run --bin headless -- [--occlusion] [--nearest=k] [--profile] [ticks] [seed] [mean|weighted|median] [angular|reynolds] [mask.png]
+//! ```
+//!
+//! Dark pixels of the optional PNG mask, stretched over the 1920x1080 world, are obstacles.
+//! With `--occlusion`, boids don't see through each other nor through obstacles.
+//! With `--nearest=k`, boids react to their k nearest neighbours instead of those within their vision range.
+//! With `--profile`, where ticks spend their time is printed to stderr at the end
+
+use std::fs::File;
+use boids::cluster::{Clustering, Linkage};
+use boids::mask::Mask;
+use boids::profile::Profiler;
+use boids::universe::Universe;
+use boids::universe::boid::{Alignment, Neighbourhood, Steering};
+
+fn main() {
+	let mut args: Vec<String> = std::env::args().collect();
+	let occlusion = args.iter().any(|arg| arg == "--occlusion");
+	let profile = args.iter().any(|arg| arg == "--profile");
+	let neighbourhood = args
+		.iter()
+		.find_map(|arg| arg.strip_prefix("--nearest="))
+		.and_then(Neighbourhood::from_name)
+		.unwrap_or(Neighbourhood::Metric);
+	args.retain(|arg| !arg.starts_with("--"));
+	let ticks: u32 = args.get(1).and_then(|arg| arg.parse().ok()).unwrap_or(1000);
+	let seed: u64 = args.get(2).and_then(|arg| arg.parse().ok()).unwrap_or(0);
+
+	let mut universe = Universe::new(None, 1920.0, 1080.0, seed);
+	universe.clustering = Some(Clustering::new(Linkage::Visibility));
+	universe.settings.occlusion = occlusion;
+	universe.settings.neighbourhood = neighbourhood;
+	if let Some(alignment) = args.get(3).and_then(|arg| Alignment::from_name(arg)) {
+		universe.settings.alignment = alignment;
+	}
+	if let Some(steering) = args.get(4).and_then(|arg| Steering::from_name(arg)) {
+		universe.settings.steering = steering;
+	}
+	if let Some(path) = args.get(5) {
+		universe.set_mask(Some(load_mask(path)));
+	}
+	if profile {
+		universe.profiler = Some(Profiler::new(ticks as usize));
+	}
+
+	println!("tick,polarization,milling,nearest_neighbour_distance,average_neighbours,clusters,largest_cluster");
+	for tick in 0..ticks {
+		universe.tick(1.0, false);
+		let metrics = universe.metrics;
+		let clusters = &universe.clustering.as_ref().unwrap().clusters;
+		println!(
+			"{},{},{},{},{},{},{}",
+			tick,
+			metrics.polarization,
+			metrics.milling,
+			metrics.nearest_neighbour_distance,
+			metrics.average_neighbours,
+			clusters.len(),
+			clusters.first().map_or(0, |cluster| cluster.size)
+		);
+	}
+
+	if let Some(profiler) = &universe.profiler {
+		eprintln!("{:<24} {:>12} {:>12}", "section", "mean", "max");
+		for (name, rolling) in profiler.sections() {
+			eprintln!("{:<24} {:>12.4} {:>12.4}", name, rolling.mean(), rolling.max());
+		}
+	}
+}
+
+fn load_mask(path: &str) -> Mask {
+	let file = File::open(path).unwrap_or_else(|error| panic!("cannot open {}: {}", path, error));
+	let mut decoder = png::Decoder::new(file);
+	decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+	let mut reader = decoder.read_info().expect("invalid png");
+	let mut buffer = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut buffer).expect("invalid png");
+	let bytes = &buffer[..info.buffer_size()];
+	let (width, height) = (info.width as usize, info.height as usize);
+	let rgba: Option<Vec<u8>> = match info.color_type {
+		png::ColorType::Rgba => Some(bytes.to_vec()),
+		png::ColorType::Rgb => Some(bytes.chunks_exact(3).flat_map(|p| vec![p[0], p[1], p[2], 255]).collect()),
+		png::ColorType::GrayscaleAlpha => Some(bytes.chunks_exact(2).flat_map(|p| vec![p[0], p[0], p[0], p[1]]).collect()),
+		_ => None
+	};
+	let mask = match rgba {
+		Some(rgba) => Mask::from_rgba(&rgba, width, height, 128),
+		None => Mask::from_grayscale(bytes, width, height, 128)
+	};
+	mask.unwrap_or_else(|error| panic!("{}: {}", path, error))
+}
@@ -0,0 +1,153 @@
+//! Statistics over angles (radians, modulo 2π)
+
+use std::f64::consts::PI;
+
+/// Circular mean, `None` when the angles cancel out
+pub fn mean(angles: &[f64]) -> Option<f64> {
+	let (sin, cos) = angles.iter().fold((0.0, 0.0), |(sin, cos), angle| (sin + angle.sin(), cos + angle.cos()));
+	from_components(sin, cos)
+}
+
+/// Circular mean of `(angle, weight)` pairs, `None` when the angles cancel out
+pub fn weighted_mean(angles: &[(f64, f64)]) -> Option<f64> {
+	let (sin, cos) = angles.iter().fold((0.0, 0.0), |(sin, cos), (angle, weight)| (sin + angle.sin() * weight, cos + angle.cos() * weight));
+	from_components(sin, cos)
+}
+
+fn from_components(sin: f64, cos: f64) -> Option<f64> {
+	if sin.abs() < 1e-12 && cos.abs() < 1e-12 {
+		None
+	} else {
+		Some(sin.atan2(cos))
+	}
+}
+
+/// Median Estimate For Circular Data, port of `js/median.js`
+///
+/// A More Efficient Way Of Obtaining A Unique Median Estimate For Circular Data
+/// 2003 / B. Sango Otieno & Christine M. Anderson-Cook
+///
+/// The median is a point such that half the observations lie on each side of the diameter
+/// through it, and more observations are close to it than to its antipode.
+/// Unlike `js/median.js`, observations are sorted numerically, and candidates are kept
+/// when ties can balance both sides (`|difsin| <= numties`) as in the paper.
+///
+/// Returns `None` for an empty sample, or when the candidates cancel out.
+pub fn median(angles: &[f64]) -> Option<f64> {
+	if angles.is_empty() {
+		return None
+	}
+
+	let mut sorted: Vec<f64> = angles.iter().map(|angle| angle.rem_euclid(PI * 2.0)).collect();
+	sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+
+	// Checks if sample size is odd or even
+	let possible_medians = if sorted.len().is_multiple_of(2) {
+		check_even(&sorted)
+	} else {
+		check_odd(&sorted)
+	};
+
+	// Checks for ties
+	let medians: Vec<f64> = possible_medians
+		.into_iter()
+		.filter(|candidate| {
+			let (positive, negative, ties) = count_sides(&sorted, *candidate, f64::sin);
+			let difsin = positive as i64 - negative as i64;
+			difsin.unsigned_abs() as usize <= ties
+		})
+		.collect();
+
+	mean(&medians)
+}
+
+/// Each observation is a possible median, if it isn't facing away from most of the sample
+fn check_odd(sorted: &[f64]) -> Vec<f64> {
+	sorted
+		.iter()
+		.copied()
+		.filter(|candidate| {
+			let (_, behind, _) = count_sides(sorted, *candidate, f64::cos);
+			behind <= (sorted.len() - 1) / 2
+		})
+		.collect()
+}
+
+/// Circular means of adjacent observations are possible medians,
+/// if at least half the sample is in front of them
+fn check_even(sorted: &[f64]) -> Vec<f64> {
+	sorted
+		.iter()
+		.zip(sorted.iter().cycle().skip(1))
+		.filter_map(|(a, b)| mean(&[*a, *b]))
+		.filter(|candidate| {
+			let (in_front, _, _) = count_sides(sorted, *candidate, f64::cos);
+			in_front >= sorted.len() / 2
+		})
+		.collect()
+}
+
+/// Number of observations for which `f(observation - center)` is positive, negative, or zero
+fn count_sides(sorted: &[f64], center: f64, f: fn(f64) -> f64) -> (usize, usize, usize) {
+	sorted.iter().fold((0, 0, 0), |(positive, negative, zero), observation| {
+		let value = f(observation - center);
+		if value.abs() < 1e-12 {
+			(positive, negative, zero + 1)
+		} else if value > 0.0 {
+			(positive + 1, negative, zero)
+		} else {
+			(positive, negative + 1, zero)
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_angle_eq(actual: f64, expected: f64) {
+		let difference = (actual - expected).rem_euclid(PI * 2.0);
+		let difference = difference.min(PI * 2.0 - difference);
+		assert!(difference < 1e-9, "expected {} got {}", expected, actual);
+	}
+
+	#[test]
+	fn median_of_odd_sample_is_middle_observation() {
+		assert_angle_eq(median(&[0.1, 0.3, 0.2]).unwrap(), 0.2);
+	}
+
+	#[test]
+	fn median_of_even_sample_is_between_middle_observations() {
+		assert_angle_eq(median(&[0.1, 0.2, 0.4, 0.5]).unwrap(), 0.3);
+	}
+
+	#[test]
+	fn median_wraps_around_zero() {
+		assert_angle_eq(median(&[PI * 2.0 - 0.2, 0.1, PI * 2.0 - 0.1]).unwrap(), -0.1);
+		assert_angle_eq(median(&[-0.3, 0.1, 0.2]).unwrap(), 0.1);
+	}
+
+	#[test]
+	fn median_ignores_outliers() {
+		let angles = [1.0, 1.1, 1.2, 1.3, 1.2 + PI / 2.0];
+		assert_angle_eq(median(&angles).unwrap(), 1.2);
+		assert!((mean(&angles).unwrap() - 1.2).abs() > 0.1);
+	}
+
+	#[test]
+	fn median_of_empty_sample_is_none() {
+		assert_eq!(median(&[]), None);
+	}
+
+	#[test]
+	fn mean_of_opposite_angles_is_none() {
+		assert_eq!(mean(&[0.0, PI]), None);
+		assert_eq!(weighted_mean(&[(0.0, 2.0), (PI, 2.0)]), None);
+	}
+
+	#[test]
+	fn weighted_mean_leans_towards_heavier_angles() {
+		let mean = weighted_mean(&[(0.0, 3.0), (PI / 2.0, 1.0)]).unwrap();
+		assert!(mean > 0.0 && mean < PI / 4.0);
+	}
+}
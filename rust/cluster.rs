@@ -0,0 +1,235 @@
+use std::collections::{HashMap, HashSet};
+use crate::flock::Flock;
+use crate::universe::boid::Boid;
+
+/// What links two boids into the same flock
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Linkage {
+	/// either boid sees the other (range and field of view)
+	Visibility,
+	/// boids are within vision range of one another, whatever their heading
+	Distance
+}
+
+impl Linkage {
+	pub fn from_name(name: &str) -> Option<Linkage> {
+		match name {
+			"visibility" => Some(Linkage::Visibility),
+			"distance" => Some(Linkage::Distance),
+			_ => None
+		}
+	}
+
+	fn links(&self, a: &Boid, b: &Boid) -> bool {
+		match self {
+			Linkage::Visibility => a.sees(b) || b.sees(a),
+			Linkage::Distance => {
+				let distance = (a.x() - b.x()).powi(2) + (a.y() - b.y()).powi(2);
+				distance <= a.vision().radius.max(b.vision().radius).powi(2)
+			}
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct Cluster {
+	/// stays the same from one tick to the next as long as the flock keeps most of its members
+	pub id: u32,
+	pub size: usize,
+	pub centroid: (f64, f64),
+	/// circular mean of the members' headings
+	pub heading: f64
+}
+
+/// Connected components of the neighbour graph
+pub struct Clustering {
+	pub linkage: Linkage,
+	pub clusters: Vec<Cluster>,
+	labels: HashMap<u32, u32>,
+	next_id: u32
+}
+
+impl Clustering {
+	pub fn new(linkage: Linkage) -> Clustering {
+		Clustering {
+			linkage,
+			clusters: vec![],
+			labels: HashMap::new(),
+			next_id: 0
+		}
+	}
+
+	/// Cluster id of a boid, by boid id
+	pub fn label(&self, boid_id: u32) -> Option<u32> {
+		self.labels.get(&boid_id).copied()
+	}
+
+	/// `candidates` must return, for a given boid, every other boid within vision range
+	/// (typically its cell of `grid_split`)
+	pub fn update<'a, F>(&mut self, flock: &Flock, candidates: F)
+	where F: Fn(&Boid) -> &'a [Boid<'a>] {
+		let mut parents: Vec<usize> = (0..flock.len()).collect();
+
+		for boid in flock.iter() {
+			let i = boid.index();
+			for other in candidates(&boid) {
+				let j = other.index();
+				if j <= i {
+					continue
+				}
+				if self.linkage.links(&boid, other) {
+					union(&mut parents, i, j);
+				}
+			}
+		}
+
+		let mut components: HashMap<usize, Vec<usize>> = HashMap::new();
+		for i in 0..flock.len() {
+			let root = find(&mut parents, i);
+			components.entry(root).or_default().push(i);
+		}
+		let mut components: Vec<Vec<usize>> = components.into_values().collect();
+		components.sort_unstable_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+
+		let mut labels = HashMap::with_capacity(flock.len());
+		let mut clusters = Vec::with_capacity(components.len());
+		let mut taken = HashSet::new();
+		for members in components {
+			let id = self.inherit_id(flock, &members, &taken);
+			taken.insert(id);
+
+			let count = members.len() as f64;
+			let (mut x, mut y, mut sin, mut cos) = (0.0, 0.0, 0.0, 0.0);
+			for &i in &members {
+				let boid = flock.get(i);
+				x += boid.x();
+				y += boid.y();
+				sin += boid.heading().sin();
+				cos += boid.heading().cos();
+				labels.insert(boid.id(), id);
+			}
+			clusters.push(Cluster {
+				id,
+				size: members.len(),
+				centroid: (x / count, y / count),
+				heading: sin.atan2(cos)
+			});
+		}
+
+		self.labels = labels;
+		self.clusters = clusters;
+	}
+
+	/// Previous id shared by most members, unless a bigger cluster already claimed it
+	fn inherit_id(&mut self, flock: &Flock, members: &[usize], taken: &HashSet<u32>) -> u32 {
+		let mut votes: HashMap<u32, usize> = HashMap::new();
+		for &i in members {
+			if let Some(&label) = self.labels.get(&flock.id[i]) {
+				*votes.entry(label).or_default() += 1;
+			}
+		}
+		let inherited = votes
+			.into_iter()
+			.filter(|(label, _)| !taken.contains(label))
+			.max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+			.map(|(label, _)| label);
+		match inherited {
+			Some(label) => label,
+			None => {
+				self.next_id += 1;
+				self.next_id
+			}
+		}
+	}
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+	let mut root = i;
+	while parents[root] != root {
+		root = parents[root];
+	}
+	let mut node = i;
+	while parents[node] != root {
+		let next = parents[node];
+		parents[node] = root;
+		node = next;
+	}
+	root
+}
+
+fn union(parents: &mut [usize], a: usize, b: usize) {
+	let root_a = find(parents, a);
+	let root_b = find(parents, b);
+	if root_a != root_b {
+		parents[root_a.max(root_b)] = root_a.min(root_b);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::rng::Rng;
+	use crate::universe::boid::{Canvas, Point};
+
+	/// Boids 100 px apart see each other, so points 60 px apart chain into one flock
+	fn flock(points: &[(f64, f64)]) -> Flock {
+		let canvas = Canvas { width: 10_000.0, height: 10_000.0, padding: 0.0, arena: None, mask: None };
+		let mut rng = Rng::new(0);
+		let mut flock = Flock::new();
+		for &(x, y) in points {
+			flock.spawn(Some(Point { x, y }), &canvas, &mut rng);
+		}
+		flock
+	}
+
+	fn cluster(clustering: &mut Clustering, flock: &Flock) {
+		let boids: Vec<Boid> = flock.iter().collect();
+		clustering.update(flock, |_| &boids[..]);
+	}
+
+	#[test]
+	fn separate_groups_are_separate_flocks() {
+		let flock = flock(&[(0.0, 0.0), (60.0, 0.0), (120.0, 0.0), (5000.0, 0.0), (5060.0, 0.0)]);
+		let mut clustering = Clustering::new(Linkage::Distance);
+		cluster(&mut clustering, &flock);
+
+		let sizes: Vec<usize> = clustering.clusters.iter().map(|cluster| cluster.size).collect();
+		assert_eq!(sizes, vec![3, 2]);
+		assert_eq!(clustering.clusters[0].centroid, (60.0, 0.0));
+		let (first, second) = (clustering.clusters[0].id, clustering.clusters[1].id);
+		assert_ne!(first, second);
+		for (i, &id) in flock.id.iter().enumerate() {
+			assert_eq!(clustering.label(id), Some(if i < 3 { first } else { second }));
+		}
+	}
+
+	#[test]
+	fn larger_half_of_a_split_keeps_the_id() {
+		let mut flock = flock(&[(0.0, 0.0), (60.0, 0.0), (120.0, 0.0), (180.0, 0.0), (240.0, 0.0), (300.0, 0.0), (360.0, 0.0)]);
+		let mut clustering = Clustering::new(Linkage::Distance);
+		cluster(&mut clustering, &flock);
+		assert_eq!(clustering.clusters.len(), 1);
+		let id = clustering.clusters[0].id;
+
+		// the last three boids fly away
+		for i in 4..7 {
+			flock.x[i] += 5000.0;
+		}
+		cluster(&mut clustering, &flock);
+		assert_eq!(clustering.clusters.len(), 2);
+		assert_eq!((clustering.clusters[0].size, clustering.clusters[0].id), (4, id));
+		assert_ne!(clustering.clusters[1].id, id);
+		assert_eq!(clustering.label(flock.id[0]), Some(id));
+		assert_eq!(clustering.label(flock.id[6]), Some(clustering.clusters[1].id));
+	}
+
+	#[test]
+	fn union_find_merges_chains() {
+		let mut parents: Vec<usize> = (0..6).collect();
+		union(&mut parents, 4, 5);
+		union(&mut parents, 1, 2);
+		union(&mut parents, 2, 5);
+		let roots: Vec<usize> = (0..6).map(|i| find(&mut parents, i)).collect();
+		assert_eq!(roots, vec![0, 1, 1, 3, 1, 1]);
+	}
+}
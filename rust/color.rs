@@ -0,0 +1,293 @@
+use std::f64::consts::PI;
+
+/// Colour packed as 0xRRGGBBAA
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rgba(pub u32);
+
+impl Rgba {
+	pub const TRANSPARENT: Rgba = Rgba(0x00000000);
+	pub const RED: Rgba = Rgba(0xff0000ff);
+
+	pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Rgba {
+		Rgba((r as u32) << 24 | (g as u32) << 16 | (b as u32) << 8 | a as u32)
+	}
+
+	pub const fn rgb(r: u8, g: u8, b: u8) -> Rgba {
+		Rgba::new(r, g, b, 0xff)
+	}
+
+	/// Fully saturated colour, `hue` in radians
+	pub fn from_hue(hue: f64) -> Rgba {
+		let sector = (hue.rem_euclid(PI * 2.0) / (PI / 3.0)) % 6.0;
+		let x = 1.0 - (sector % 2.0 - 1.0).abs();
+		let (r, g, b) = match sector as u32 {
+			0 => (1.0, x, 0.0),
+			1 => (x, 1.0, 0.0),
+			2 => (0.0, 1.0, x),
+			3 => (0.0, x, 1.0),
+			4 => (x, 0.0, 1.0),
+			_ => (1.0, 0.0, x)
+		};
+		Rgba::rgb(channel(r), channel(g), channel(b))
+	}
+
+	pub fn r(&self) -> u8 { (self.0 >> 24) as u8 }
+	pub fn g(&self) -> u8 { (self.0 >> 16) as u8 }
+	pub fn b(&self) -> u8 { (self.0 >> 8) as u8 }
+	pub fn a(&self) -> u8 { self.0 as u8 }
+
+	/// Linear interpolation between two colours, `t` clamped to [0, 1]
+	pub fn lerp(&self, other: Rgba, t: f64) -> Rgba {
+		let t = t.clamp(0.0, 1.0);
+		let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+		Rgba::new(
+			mix(self.r(), other.r()),
+			mix(self.g(), other.g()),
+			mix(self.b(), other.b()),
+			mix(self.a(), other.a())
+		)
+	}
+
+	/// Each channel, alpha included, rounded to the closest of `2^bits` evenly spaced levels
+	pub fn quantise(&self, bits: u32) -> Rgba {
+		let levels = ((1 << bits) - 1) as f64;
+		let round = |value: u8| ((value as f64 / 255.0 * levels).round() / levels * 255.0).round() as u8;
+		Rgba::new(round(self.r()), round(self.g()), round(self.b()), round(self.a()))
+	}
+
+	/// String accepted by `CanvasRenderingContext2d.fillStyle`
+	pub fn to_css(&self) -> String {
+		format!("#{:08x}", self.0)
+	}
+}
+
+fn channel(value: f64) -> u8 {
+	(value * 255.0).round() as u8
+}
+
+/// How `Universe::render` picks the colour of each boid
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorMode {
+	/// colour assigned at creation from the boid's agility
+	Species,
+	/// slow boids are blue, fast ones are red
+	Speed,
+	/// hue wheel following the boid's heading
+	Heading,
+	/// isolated boids are dark, crowded ones are bright
+	Neighbours,
+	/// one of the named colours of `js/colors.js`, picked by id
+	Palette,
+	/// one palette colour per flock, see `Clustering`
+	Cluster
+}
+
+impl ColorMode {
+	pub fn from_name(name: &str) -> Option<ColorMode> {
+		match name {
+			"species" => Some(ColorMode::Species),
+			"speed" => Some(ColorMode::Speed),
+			"heading" => Some(ColorMode::Heading),
+			"neighbours" => Some(ColorMode::Neighbours),
+			"palette" => Some(ColorMode::Palette),
+			"cluster" => Some(ColorMode::Cluster),
+			_ => None
+		}
+	}
+}
+
+/// Same colours, in the same order, as `js/colors.js`
+pub const PALETTE: [Rgba; 133] = [
+	Rgba(0xf0f8ffff), // AliceBlue
+	Rgba(0x00ffffff), // Aqua
+	Rgba(0x7fffd4ff), // Aquamarine
+	Rgba(0xffe4c4ff), // Bisque
+	Rgba(0x000000ff), // Black
+	Rgba(0xffebcdff), // BlanchedAlmond
+	Rgba(0x0000ffff), // Blue
+	Rgba(0x8a2be2ff), // BlueViolet
+	Rgba(0xa52a2aff), // Brown
+	Rgba(0xdeb887ff), // BurlyWood
+	Rgba(0x5f9ea0ff), // CadetBlue
+	Rgba(0x7fff00ff), // Chartreuse
+	Rgba(0xd2691eff), // Chocolate
+	Rgba(0xff7f50ff), // Coral
+	Rgba(0x6495edff), // CornflowerBlue
+	Rgba(0xfff8dcff), // Cornsilk
+	Rgba(0xdc143cff), // Crimson
+	Rgba(0x00ffffff), // Cyan
+	Rgba(0x00008bff), // DarkBlue
+	Rgba(0x008b8bff), // DarkCyan
+	Rgba(0xb8860bff), // DarkGoldenRod
+	Rgba(0xa9a9a9ff), // DarkGray
+	Rgba(0xa9a9a9ff), // DarkGrey
+	Rgba(0x006400ff), // DarkGreen
+	Rgba(0xbdb76bff), // DarkKhaki
+	Rgba(0x8b008bff), // DarkMagenta
+	Rgba(0x556b2fff), // DarkOliveGreen
+	Rgba(0xff8c00ff), // DarkOrange
+	Rgba(0x9932ccff), // DarkOrchid
+	Rgba(0x8b0000ff), // DarkRed
+	Rgba(0xe9967aff), // DarkSalmon
+	Rgba(0x8fbc8fff), // DarkSeaGreen
+	Rgba(0x483d8bff), // DarkSlateBlue
+	Rgba(0x2f4f4fff), // DarkSlateGray
+	Rgba(0x2f4f4fff), // DarkSlateGrey
+	Rgba(0x00ced1ff), // DarkTurquoise
+	Rgba(0x9400d3ff), // DarkViolet
+	Rgba(0xff1493ff), // DeepPink
+	Rgba(0x00bfffff), // DeepSkyBlue
+	Rgba(0x696969ff), // DimGray
+	Rgba(0x696969ff), // DimGrey
+	Rgba(0x1e90ffff), // DodgerBlue
+	Rgba(0xb22222ff), // FireBrick
+	Rgba(0x228b22ff), // ForestGreen
+	Rgba(0xff00ffff), // Fuchsia
+	Rgba(0xdcdcdcff), // Gainsboro
+	Rgba(0xf8f8ffff), // GhostWhite
+	Rgba(0xffd700ff), // Gold
+	Rgba(0xdaa520ff), // GoldenRod
+	Rgba(0x808080ff), // Gray
+	Rgba(0x808080ff), // Grey
+	Rgba(0x008000ff), // Green
+	Rgba(0xadff2fff), // GreenYellow
+	Rgba(0xff69b4ff), // HotPink
+	Rgba(0xcd5c5cff), // IndianRed
+	Rgba(0x4b0082ff), // Indigo
+	Rgba(0xfffff0ff), // Ivory
+	Rgba(0xf0e68cff), // Khaki
+	Rgba(0xe6e6faff), // Lavender
+	Rgba(0xfff0f5ff), // LavenderBlush
+	Rgba(0x7cfc00ff), // LawnGreen
+	Rgba(0xadd8e6ff), // LightBlue
+	Rgba(0xf08080ff), // LightCoral
+	Rgba(0xfafad2ff), // LightGoldenRodYellow
+	Rgba(0xd3d3d3ff), // LightGray
+	Rgba(0xd3d3d3ff), // LightGrey
+	Rgba(0x90ee90ff), // LightGreen
+	Rgba(0xffb6c1ff), // LightPink
+	Rgba(0xffa07aff), // LightSalmon
+	Rgba(0x20b2aaff), // LightSeaGreen
+	Rgba(0x87cefaff), // LightSkyBlue
+	Rgba(0x778899ff), // LightSlateGray
+	Rgba(0x778899ff), // LightSlateGrey
+	Rgba(0xb0c4deff), // LightSteelBlue
+	Rgba(0x00ff00ff), // Lime
+	Rgba(0x32cd32ff), // LimeGreen
+	Rgba(0xff00ffff), // Magenta
+	Rgba(0x800000ff), // Maroon
+	Rgba(0x66cdaaff), // MediumAquaMarine
+	Rgba(0x0000cdff), // MediumBlue
+	Rgba(0xba55d3ff), // MediumOrchid
+	Rgba(0x9370dbff), // MediumPurple
+	Rgba(0x3cb371ff), // MediumSeaGreen
+	Rgba(0x7b68eeff), // MediumSlateBlue
+	Rgba(0x00fa9aff), // MediumSpringGreen
+	Rgba(0x48d1ccff), // MediumTurquoise
+	Rgba(0xc71585ff), // MediumVioletRed
+	Rgba(0x191970ff), // MidnightBlue
+	Rgba(0xffe4e1ff), // MistyRose
+	Rgba(0x000080ff), // Navy
+	Rgba(0x808000ff), // Olive
+	Rgba(0x6b8e23ff), // OliveDrab
+	Rgba(0xffa500ff), // Orange
+	Rgba(0xff4500ff), // OrangeRed
+	Rgba(0xda70d6ff), // Orchid
+	Rgba(0x98fb98ff), // PaleGreen
+	Rgba(0xafeeeeff), // PaleTurquoise
+	Rgba(0xdb7093ff), // PaleVioletRed
+	Rgba(0xffefd5ff), // PapayaWhip
+	Rgba(0xffdab9ff), // PeachPuff
+	Rgba(0xcd853fff), // Peru
+	Rgba(0xffc0cbff), // Pink
+	Rgba(0xdda0ddff), // Plum
+	Rgba(0xb0e0e6ff), // PowderBlue
+	Rgba(0x800080ff), // Purple
+	Rgba(0x663399ff), // RebeccaPurple
+	Rgba(0xff0000ff), // Red
+	Rgba(0xbc8f8fff), // RosyBrown
+	Rgba(0x4169e1ff), // RoyalBlue
+	Rgba(0x8b4513ff), // SaddleBrown
+	Rgba(0xfa8072ff), // Salmon
+	Rgba(0xf4a460ff), // SandyBrown
+	Rgba(0x2e8b57ff), // SeaGreen
+	Rgba(0xfff5eeff), // SeaShell
+	Rgba(0xa0522dff), // Sienna
+	Rgba(0xc0c0c0ff), // Silver
+	Rgba(0x87ceebff), // SkyBlue
+	Rgba(0x6a5acdff), // SlateBlue
+	Rgba(0x708090ff), // SlateGray
+	Rgba(0x708090ff), // SlateGrey
+	Rgba(0xfffafaff), // Snow
+	Rgba(0x00ff7fff), // SpringGreen
+	Rgba(0x4682b4ff), // SteelBlue
+	Rgba(0xd2b48cff), // Tan
+	Rgba(0x008080ff), // Teal
+	Rgba(0xd8bfd8ff), // Thistle
+	Rgba(0xff6347ff), // Tomato
+	Rgba(0x40e0d0ff), // Turquoise
+	Rgba(0xee82eeff), // Violet
+	Rgba(0xf5deb3ff), // Wheat
+	Rgba(0xffffffff), // White
+	Rgba(0xf5f5f5ff), // WhiteSmoke
+	Rgba(0x9acd32ff), // YellowGreen
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn channels_are_packed_red_first() {
+		let color = Rgba::new(0x12, 0x34, 0x56, 0x78);
+		assert_eq!(color, Rgba(0x12345678));
+		assert_eq!((color.r(), color.g(), color.b(), color.a()), (0x12, 0x34, 0x56, 0x78));
+		assert_eq!(Rgba::rgb(0xff, 0, 0), Rgba::RED);
+	}
+
+	#[test]
+	fn lerp_goes_from_one_colour_to_the_other() {
+		let (black, white) = (Rgba::rgb(0, 0, 0), Rgba::rgb(255, 255, 255));
+		assert_eq!(black.lerp(white, 0.0), black);
+		assert_eq!(black.lerp(white, 1.0), white);
+		assert_eq!(black.lerp(white, 0.5), Rgba::rgb(128, 128, 128));
+		assert_eq!(Rgba::TRANSPARENT.lerp(Rgba::RED, 0.5), Rgba::new(128, 0, 0, 128));
+	}
+
+	#[test]
+	fn lerp_clamps_t() {
+		let (black, white) = (Rgba::rgb(0, 0, 0), Rgba::rgb(255, 255, 255));
+		assert_eq!(black.lerp(white, -3.0), black);
+		assert_eq!(black.lerp(white, 7.0), white);
+	}
+
+	#[test]
+	fn css_keeps_leading_zeros_and_alpha() {
+		assert_eq!(Rgba::RED.to_css(), "#ff0000ff");
+		assert_eq!(Rgba::new(0, 0x0a, 0, 0x80).to_css(), "#000a0080");
+		assert_eq!(Rgba::TRANSPARENT.to_css(), "#00000000");
+	}
+
+	#[test]
+	fn hue_wheel_starts_red_and_wraps() {
+		assert_eq!(Rgba::from_hue(0.0), Rgba::RED);
+		assert_eq!(Rgba::from_hue(PI * 2.0 / 3.0), Rgba::rgb(0, 255, 0));
+		assert_eq!(Rgba::from_hue(PI * 2.0), Rgba::RED);
+		assert_eq!(Rgba::from_hue(- PI * 2.0 / 3.0), Rgba::rgb(0, 0, 255));
+	}
+
+	#[test]
+	fn modes_are_named_as_in_the_page() {
+		for &(name, mode) in &[
+			("species", ColorMode::Species),
+			("speed", ColorMode::Speed),
+			("heading", ColorMode::Heading),
+			("neighbours", ColorMode::Neighbours),
+			("palette", ColorMode::Palette),
+			("cluster", ColorMode::Cluster)
+		] {
+			assert_eq!(ColorMode::from_name(name), Some(mode));
+		}
+		assert_eq!(ColorMode::from_name("rainbow"), None);
+	}
+}
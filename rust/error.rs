@@ -0,0 +1,133 @@
+use wasm_bindgen::prelude::*;
+
+/// What went wrong in a call to the wasm API, as the `code` of the `Error` it throws
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorCode {
+	/// `send_context` hasn't been called yet, or the current universe was dropped
+	NotInitialised = 1,
+	/// sizes that are negative, zero or not finite, or arrays whose length doesn't match their dimensions
+	InvalidDimensions = 2,
+	/// NaN or infinite input
+	NotANumber = 3,
+	/// no universe, attractor or rule by that handle, id or name
+	NotFound = 4,
+	/// name that isn't one of the options of a setting
+	UnknownName = 5
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Error {
+	pub code: ErrorCode,
+	pub message: String
+}
+
+impl Error {
+	pub fn new(code: ErrorCode, message: impl Into<String>) -> Error {
+		Error { code, message: message.into() }
+	}
+
+	pub fn not_found(what: &str, id: impl std::fmt::Display) -> Error {
+		Error::new(ErrorCode::NotFound, format!("no {} {}", what, id))
+	}
+
+	pub fn unknown_name(setting: &str, name: &str) -> Error {
+		Error::new(ErrorCode::UnknownName, format!("unknown {} \"{}\"", setting, name))
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{:?}: {}", self.code, self.message)
+	}
+}
+
+/// A JS `Error` named after the code, with the code itself as its `code` property
+impl From<Error> for JsValue {
+	fn from(error: Error) -> JsValue {
+		let js_error = js_sys::Error::new(&error.message);
+		js_error.set_name(&format!("{:?}", error.code));
+		let _ = js_sys::Reflect::set(&js_error, &JsValue::from_str("code"), &JsValue::from(error.code as u32));
+		js_error.into()
+	}
+}
+
+/// `value` if it is a finite number, `ErrorCode::NotANumber` otherwise
+pub fn finite(name: &str, value: f64) -> Result<f64, Error> {
+	if value.is_finite() {
+		Ok(value)
+	} else {
+		Err(Error::new(ErrorCode::NotANumber, format!("{} is {}", name, value)))
+	}
+}
+
+/// Every value finite, see `finite`
+pub fn all_finite(name: &str, values: &[f64]) -> Result<(), Error> {
+	match values.iter().position(|value| !value.is_finite()) {
+		Some(index) => Err(Error::new(ErrorCode::NotANumber, format!("{}[{}] is {}", name, index, values[index]))),
+		None => Ok(())
+	}
+}
+
+/// Canvas sizes must be finite and positive
+pub fn dimensions(width: f64, height: f64) -> Result<(), Error> {
+	if width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0 {
+		Ok(())
+	} else {
+		Err(Error::new(ErrorCode::InvalidDimensions, format!("{} by {} canvas", width, height)))
+	}
+}
+
+/// `ErrorCode::InvalidDimensions` unless an array holds as many items as its dimensions say
+pub fn length(name: &str, actual: usize, expected: usize) -> Result<(), Error> {
+	if actual == expected {
+		Ok(())
+	} else {
+		Err(Error::new(ErrorCode::InvalidDimensions, format!("{} holds {} values instead of {}", name, actual, expected)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finite_rejects_nan_and_infinities() {
+		assert_eq!(finite("x", 1.5), Ok(1.5));
+		assert_eq!(finite("x", - 0.0), Ok(- 0.0));
+		for value in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+			let error = finite("x", *value).unwrap_err();
+			assert_eq!(error.code, ErrorCode::NotANumber);
+			assert!(error.message.starts_with("x is "), "{}", error.message);
+		}
+	}
+
+	#[test]
+	fn all_finite_points_at_the_first_bad_value() {
+		assert_eq!(all_finite("points", &[]), Ok(()));
+		assert_eq!(all_finite("points", &[0.0, - 3.0, 1e300]), Ok(()));
+		let error = all_finite("points", &[1.0, f64::NEG_INFINITY, f64::NAN]).unwrap_err();
+		assert_eq!(error, Error::new(ErrorCode::NotANumber, "points[1] is -inf"));
+		assert_eq!(all_finite("points", &[f64::NAN]).unwrap_err().message, "points[0] is NaN");
+	}
+
+	#[test]
+	fn dimensions_must_be_positive_and_finite() {
+		assert_eq!(dimensions(800.0, 600.0), Ok(()));
+		assert_eq!(dimensions(0.5, 0.5), Ok(()));
+		for (width, height) in &[(0.0, 600.0), (800.0, 0.0), (0.0, 0.0), (- 1.0, 600.0), (f64::NAN, 600.0), (800.0, f64::INFINITY)] {
+			assert_eq!(dimensions(*width, *height).unwrap_err().code, ErrorCode::InvalidDimensions, "{} by {}", width, height);
+		}
+	}
+
+	#[test]
+	fn length_must_match_exactly() {
+		assert_eq!(length("vectors", 0, 0), Ok(()));
+		assert_eq!(length("vectors", 8, 8), Ok(()));
+		assert_eq!(length("vectors", 7, 8).unwrap_err(), Error::new(ErrorCode::InvalidDimensions, "vectors holds 7 values instead of 8"));
+		assert_eq!(length("vectors", 9, 8).unwrap_err().code, ErrorCode::InvalidDimensions);
+		// a grid without columns expects nothing
+		let (columns, rows) = (0, 3);
+		assert_eq!(length("vectors", 2, columns * rows * 2).unwrap_err().code, ErrorCode::InvalidDimensions);
+	}
+}
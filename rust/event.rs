@@ -0,0 +1,66 @@
+use wasm_bindgen::prelude::*;
+use crate::error::Error;
+use crate::metrics::Metrics;
+
+/// What the simulation tells its host, through the callback given to `set_event_listener`.
+/// Each event reaches JS as a plain object with a `type` and the fields of its variant
+#[derive(Clone, Debug)]
+pub enum Event {
+	/// `{type: "tick", deltaTime}`, after a tick of `request_tick`, in ms since the previous one
+	Tick { delta_time: f64 },
+	/// `{type: "frame", deltaTime}`, after `request_frame` drew the universe
+	Frame { delta_time: f64 },
+	/// `{type: "boidAdded", count}` with the number of boids after the addition
+	BoidAdded { count: u32 },
+	/// `{type: "boidRemoved", count}` with the number of boids after the removal
+	BoidRemoved { count: u32 },
+	/// `{type: "metrics", polarization, milling, nearestNeighbourDistance, averageNeighbours}`,
+	/// after each tick of the 2D flock
+	Metrics(Metrics),
+	/// `{type: "error", name, code, message}`, for every error thrown by the API
+	Error(Error)
+}
+
+impl Event {
+	/// Value of the `type` field
+	pub fn kind(&self) -> &'static str {
+		match self {
+			Event::Tick { .. } => "tick",
+			Event::Frame { .. } => "frame",
+			Event::BoidAdded { .. } => "boidAdded",
+			Event::BoidRemoved { .. } => "boidRemoved",
+			Event::Metrics(_) => "metrics",
+			Event::Error(_) => "error"
+		}
+	}
+}
+
+impl From<Event> for JsValue {
+	fn from(event: Event) -> JsValue {
+		let object = js_sys::Object::new();
+		let set = |key: &str, value: JsValue| {
+			let _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &value);
+		};
+		set("type", JsValue::from_str(event.kind()));
+		match event {
+			Event::Tick { delta_time } | Event::Frame { delta_time } => {
+				set("deltaTime", delta_time.into());
+			},
+			Event::BoidAdded { count } | Event::BoidRemoved { count } => {
+				set("count", count.into());
+			},
+			Event::Metrics(metrics) => {
+				set("polarization", metrics.polarization.into());
+				set("milling", metrics.milling.into());
+				set("nearestNeighbourDistance", metrics.nearest_neighbour_distance.into());
+				set("averageNeighbours", metrics.average_neighbours.into());
+			},
+			Event::Error(error) => {
+				set("name", JsValue::from_str(&format!("{:?}", error.code)));
+				set("code", (error.code as u32).into());
+				set("message", JsValue::from_str(&error.message));
+			}
+		}
+		object.into()
+	}
+}
@@ -0,0 +1,145 @@
+use std::f64::consts::PI;
+use crate::angle::Angle;
+use crate::color::Rgba;
+use crate::rng::Rng;
+use crate::universe::boid::{Boid, Canvas, Cone, Motion, Point};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+	pub min: f64,
+	pub max: f64
+}
+
+/// Attributes drawn once, when a boid spawns
+#[derive(Clone, Copy, Debug)]
+pub struct Traits {
+	pub vision: Cone,
+	pub angular_speed: Limits,
+	pub linear_speed: Limits,
+	pub size: f64,
+	pub width: f64,
+	pub color: Rgba,
+	/// how strongly attractors, repellers and waypoints affect this boid
+	pub goal_seeking: f64
+}
+
+/// Every boid of a universe, one array per attribute. Boid `i` is at index `i` of each array,
+/// and `Flock::get` returns a `Boid` view over it.
+#[derive(Clone, Default)]
+pub struct Flock {
+	pub id: Vec<u32>,
+	pub x: Vec<f64>,
+	pub y: Vec<f64>,
+	/// heading, in [0, 2π)
+	pub angle: Vec<f64>,
+	pub angular_speed: Vec<f64>,
+	pub linear_speed: Vec<f64>,
+	pub weight: Vec<f64>,
+	pub traits: Vec<Traits>,
+	/// heading the body is drawn with, catching up with `angle`
+	pub drawing_angle: Vec<f64>,
+	/// number of boids seen during the last update
+	pub neighbours: Vec<usize>,
+	pub waypoints_reached: Vec<usize>,
+	next_id: u32
+}
+
+impl Flock {
+	pub fn new() -> Flock {
+		Flock::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.id.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.id.is_empty()
+	}
+
+	pub fn get(&self, index: usize) -> Boid<'_> {
+		Boid::new(self, index)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = Boid<'_>> + '_ {
+		(0..self.len()).map(move |index| Boid::new(self, index))
+	}
+
+	/// New boid with random traits, heading and speeds, at a random point of the canvas
+	/// unless `point` is given. Returns its index
+	pub fn spawn(&mut self, point: Option<Point>, canvas: &Canvas, rng: &mut Rng) -> usize {
+		let weight = 1.0 + rng.random();
+		let size = 10.0 + rng.random().min(2.0) * 15.0;
+		let point = point.unwrap_or_else(|| Point {
+			x: rng.random() * canvas.width,
+			y: rng.random() * canvas.height
+		});
+		let angle = Angle::modulo(rng.random() * PI * 2.0);
+		let linear = rng.random();
+		let angular = rng.random();
+
+		let angular_speed = Limits {
+			min: 0.0,
+			max: PI * 2.0 / 45.0 * (angular + 1.0)
+		};
+		let species = angular_speed.max * 45.0 / (PI * 2.0) - 1.0;
+		let purple = (species * 180.0).round() as u8;
+		let green = ((1.0 - species) * 180.0).round() as u8;
+		let traits = Traits {
+			vision: Cone {
+				radius: 100.0,
+				radians: 1.25 * PI
+			},
+			angular_speed,
+			linear_speed: Limits {
+				min: 0.2,
+				max: 2.0 + linear
+			},
+			size,
+			width: 5.0 + (1.0 - linear).max(0.0) * 10.0,
+			color: Rgba::rgb(purple, green, purple),
+			goal_seeking: 1.0
+		};
+
+		self.next_id += 1;
+		self.id.push(self.next_id);
+		self.x.push(point.x);
+		self.y.push(point.y);
+		self.angle.push(angle);
+		self.angular_speed.push(0.0);
+		self.linear_speed.push(traits.linear_speed.min);
+		self.weight.push(weight);
+		self.traits.push(traits);
+		self.drawing_angle.push(angle);
+		self.neighbours.push(0);
+		self.waypoints_reached.push(0);
+		self.len() - 1
+	}
+
+	/// The last boid takes the place of the removed one, so indices past `len` are the only ones invalidated
+	pub fn remove(&mut self, index: usize) {
+		self.id.swap_remove(index);
+		self.x.swap_remove(index);
+		self.y.swap_remove(index);
+		self.angle.swap_remove(index);
+		self.angular_speed.swap_remove(index);
+		self.linear_speed.swap_remove(index);
+		self.weight.swap_remove(index);
+		self.traits.swap_remove(index);
+		self.drawing_angle.swap_remove(index);
+		self.neighbours.swap_remove(index);
+		self.waypoints_reached.swap_remove(index);
+	}
+
+	/// Writes back the outcome of `Boid::update`
+	pub fn apply(&mut self, index: usize, motion: &Motion) {
+		self.x[index] = motion.point.x;
+		self.y[index] = motion.point.y;
+		self.angle[index] = motion.angle;
+		self.angular_speed[index] = motion.angular_speed;
+		self.linear_speed[index] = motion.linear_speed;
+		self.drawing_angle[index] = motion.drawing_angle;
+		self.neighbours[index] = motion.neighbours;
+		self.waypoints_reached[index] = motion.waypoints_reached;
+	}
+}
@@ -0,0 +1,162 @@
+use std::f64::consts::PI;
+use crate::universe::boid::Canvas;
+
+/// Vector field over the world, in pixels per frame
+#[derive(Clone, Debug)]
+pub enum FlowField {
+	Still,
+	/// same vector everywhere
+	Uniform { x: f64, y: f64 },
+	/// circular current around a center, clockwise for a positive strength,
+	/// strongest at `radius` and fading away on both sides
+	Vortex { x: f64, y: f64, strength: f64, radius: f64 },
+	/// direction drawn from smooth noise that evolves over time
+	Noise { scale: f64, strength: f64, speed: f64, seed: u32 },
+	/// `columns * rows` vectors spread over the canvas, row by row, interpolated in between
+	Grid { columns: usize, rows: usize, vectors: Vec<(f64, f64)> }
+}
+
+impl FlowField {
+	/// Vector at (x, y), `time` in frames
+	pub fn at(&self, x: f64, y: f64, time: f64, canvas: &Canvas) -> (f64, f64) {
+		match self {
+			FlowField::Still => (0.0, 0.0),
+			FlowField::Uniform { x, y } => (*x, *y),
+			FlowField::Vortex { x: center_x, y: center_y, strength, radius } => {
+				let dx = x - center_x;
+				let dy = y - center_y;
+				let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+				if distance == 0.0 {
+					return (0.0, 0.0)
+				}
+				let ratio = distance / radius;
+				let magnitude = strength * ratio * (1.0 - ratio).exp();
+				(- dy / distance * magnitude, dx / distance * magnitude)
+			},
+			FlowField::Noise { scale, strength, speed, seed } => {
+				let angle = value_noise(x * scale, y * scale, time * speed, *seed) * PI * 4.0;
+				(angle.cos() * strength, angle.sin() * strength)
+			},
+			FlowField::Grid { columns, rows, vectors } => {
+				if *columns == 0 || *rows == 0 || vectors.len() < columns * rows {
+					return (0.0, 0.0)
+				}
+				let column = (x / canvas.width * (*columns - 1) as f64).max(0.0).min((*columns - 1) as f64);
+				let row = (y / canvas.height * (*rows - 1) as f64).max(0.0).min((*rows - 1) as f64);
+				let (left, top) = (column.floor() as usize, row.floor() as usize);
+				let (right, bottom) = ((left + 1).min(columns - 1), (top + 1).min(rows - 1));
+				let (tx, ty) = (column - left as f64, row - top as f64);
+				let get = |column: usize, row: usize| vectors[row * columns + column];
+				let lerp = |a: (f64, f64), b: (f64, f64), t: f64| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+				lerp(
+					lerp(get(left, top), get(right, top), tx),
+					lerp(get(left, bottom), get(right, bottom), tx),
+					ty
+				)
+			}
+		}
+	}
+}
+
+/// How boids respond to the flow field
+#[derive(Clone, Debug)]
+pub struct Flow {
+	pub field: FlowField,
+	/// fraction of the flow added to the boids' position each frame
+	pub drift: f64,
+	/// angular speed, per unit of flow, at which boids turn to face the flow
+	pub heading_bias: f64
+}
+
+impl Default for Flow {
+	fn default() -> Flow {
+		Flow {
+			field: FlowField::Still,
+			drift: 1.0,
+			heading_bias: 0.01
+		}
+	}
+}
+
+/// Smooth noise in [0, 1] from hashed values on an integer lattice
+fn value_noise(x: f64, y: f64, z: f64, seed: u32) -> f64 {
+	let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+	let (tx, ty, tz) = (smooth(x - x0), smooth(y - y0), smooth(z - z0));
+	let (x0, y0, z0) = (x0 as i64, y0 as i64, z0 as i64);
+	let corner = |dx: i64, dy: i64, dz: i64| hash(x0 + dx, y0 + dy, z0 + dz, seed);
+	let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+	let plane = |dz: i64| lerp(
+		lerp(corner(0, 0, dz), corner(1, 0, dz), tx),
+		lerp(corner(0, 1, dz), corner(1, 1, dz), tx),
+		ty
+	);
+	lerp(plane(0), plane(1), tz)
+}
+
+fn smooth(t: f64) -> f64 {
+	t * t * (3.0 - 2.0 * t)
+}
+
+fn hash(x: i64, y: i64, z: i64, seed: u32) -> f64 {
+	let mut h = (x as u64).wrapping_mul(0x9e3779b97f4a7c15)
+		^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+		^ (z as u64).wrapping_mul(0x165667b19e3779f9)
+		^ seed as u64;
+	h ^= h >> 33;
+	h = h.wrapping_mul(0xff51afd7ed558ccd);
+	h ^= h >> 33;
+	(h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn canvas() -> Canvas {
+		Canvas { width: 100.0, height: 50.0, padding: 0.0, arena: None, mask: None }
+	}
+
+	fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+		(a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+	}
+
+	#[test]
+	fn vortex_peaks_at_its_radius() {
+		let vortex = FlowField::Vortex { x: 0.0, y: 0.0, strength: 2.0, radius: 10.0 };
+		let speed = |distance: f64| {
+			let (x, y) = vortex.at(distance, 0.0, 0.0, &canvas());
+			(x.powi(2) + y.powi(2)).sqrt()
+		};
+		assert_eq!(vortex.at(0.0, 0.0, 0.0, &canvas()), (0.0, 0.0));
+		assert!((speed(10.0) - 2.0).abs() < 1e-9);
+		assert!(speed(5.0) < speed(10.0) && speed(20.0) < speed(10.0));
+		assert!(speed(1000.0) < 1e-9);
+		// tangential: east of the center the current points along +y
+		assert!(close(vortex.at(10.0, 0.0, 0.0, &canvas()), (0.0, 2.0)));
+		assert!(close(vortex.at(0.0, 10.0, 0.0, &canvas()), (-2.0, 0.0)));
+	}
+
+	#[test]
+	fn grid_interpolates_and_holds_its_edges() {
+		let grid = FlowField::Grid { columns: 2, rows: 2, vectors: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] };
+		let canvas = canvas();
+		assert!(close(grid.at(0.0, 0.0, 0.0, &canvas), (0.0, 0.0)));
+		assert!(close(grid.at(100.0, 50.0, 0.0, &canvas), (1.0, 1.0)));
+		assert!(close(grid.at(50.0, 25.0, 0.0, &canvas), (0.5, 0.5)));
+		assert!(close(grid.at(100.0, 0.0, 0.0, &canvas), (1.0, 0.0)));
+		// beyond the canvas the nearest edge holds
+		assert!(close(grid.at(-30.0, 25.0, 0.0, &canvas), (0.0, 0.5)));
+		assert!(close(grid.at(150.0, 80.0, 0.0, &canvas), (1.0, 1.0)));
+	}
+
+	#[test]
+	fn degenerate_grids_are_still() {
+		let canvas = canvas();
+		let single = FlowField::Grid { columns: 1, rows: 1, vectors: vec![(3.0, 4.0)] };
+		assert!(close(single.at(80.0, 10.0, 0.0, &canvas), (3.0, 4.0)));
+		let short = FlowField::Grid { columns: 2, rows: 2, vectors: vec![(1.0, 1.0)] };
+		assert_eq!(short.at(10.0, 10.0, 0.0, &canvas), (0.0, 0.0));
+		let empty = FlowField::Grid { columns: 0, rows: 3, vectors: vec![] };
+		assert_eq!(empty.at(10.0, 10.0, 0.0, &canvas), (0.0, 0.0));
+	}
+}
@@ -0,0 +1,149 @@
+use std::f64::consts::PI;
+use crate::angle::Angle;
+
+/// User-placed point boids are drawn to (positive strength) or pushed away from (negative strength)
+#[derive(Clone, Debug)]
+pub struct Attractor {
+	pub id: u32,
+	pub x: f64,
+	pub y: f64,
+	pub strength: f64,
+	/// no effect beyond this distance
+	pub radius: f64
+}
+
+impl Attractor {
+	/// Influence on a boid at (x, y): `strength` at the center, fading to 0 at `radius`
+	pub fn pull(&self, x: f64, y: f64) -> f64 {
+		let distance = ((self.x - x).powi(2) + (self.y - y).powi(2)).sqrt();
+		if distance >= self.radius {
+			0.0
+		} else {
+			self.strength * (1.0 - distance / self.radius)
+		}
+	}
+}
+
+/// Attractors, repellers and a route of waypoints that every boid follows in order
+pub struct Goals {
+	pub attractors: Vec<Attractor>,
+	pub waypoints: Vec<(f64, f64)>,
+	/// distance at which a waypoint counts as reached
+	pub reach: f64,
+	next_id: u32
+}
+
+impl Goals {
+	pub fn new() -> Goals {
+		Goals {
+			attractors: vec![],
+			waypoints: vec![],
+			reach: 50.0,
+			next_id: 0
+		}
+	}
+
+	pub fn add(&mut self, x: f64, y: f64, strength: f64, radius: f64) -> u32 {
+		self.next_id += 1;
+		self.attractors.push(Attractor {
+			id: self.next_id,
+			x,
+			y,
+			strength,
+			radius
+		});
+		self.next_id
+	}
+
+	/// Returns whether an attractor with that id was found
+	pub fn move_to(&mut self, id: u32, x: f64, y: f64) -> bool {
+		match self.attractors.iter_mut().find(|attractor| attractor.id == id) {
+			Some(attractor) => {
+				attractor.x = x;
+				attractor.y = y;
+				true
+			},
+			None => false
+		}
+	}
+
+	/// Returns whether an attractor with that id was found
+	pub fn remove(&mut self, id: u32) -> bool {
+		let length = self.attractors.len();
+		self.attractors.retain(|attractor| attractor.id != id);
+		self.attractors.len() != length
+	}
+
+	/// Closest attractor whose center is within `tolerance` of (x, y), for picking with the mouse
+	pub fn find_at(&self, x: f64, y: f64, tolerance: f64) -> Option<u32> {
+		self.attractors
+			.iter()
+			.map(|attractor| (attractor.id, ((attractor.x - x).powi(2) + (attractor.y - y).powi(2)).sqrt()))
+			.filter(|(_, distance)| *distance <= tolerance)
+			.min_by(|a, b| a.1.total_cmp(&b.1))
+			.map(|(id, _)| id)
+	}
+
+	pub fn draw(&self, context: &web_sys::CanvasRenderingContext2d) {
+		let alpha = context.global_alpha();
+		for attractor in &self.attractors {
+			let color = if attractor.strength >= 0.0 { "green" } else { "red" };
+			context.set_fill_style_str(color);
+			context.set_global_alpha(0.07);
+			context.begin_path();
+			let _ = context.arc(attractor.x, attractor.y, attractor.radius, 0.0, PI * 2.0);
+			context.fill();
+			context.set_global_alpha(alpha);
+			context.begin_path();
+			let _ = context.arc(attractor.x, attractor.y, 6.0, 0.0, PI * 2.0);
+			context.fill();
+		}
+
+		if !self.waypoints.is_empty() {
+			context.set_stroke_style_str("gray");
+			context.begin_path();
+			for (x, y) in &self.waypoints {
+				context.line_to(*x, *y);
+			}
+			context.close_path();
+			context.stroke();
+		}
+	}
+
+	/// Waypoint a boid should head to, given how many it already reached
+	pub fn waypoint(&self, reached: usize) -> Option<(f64, f64)> {
+		if self.waypoints.is_empty() {
+			None
+		} else {
+			Some(self.waypoints[reached % self.waypoints.len()])
+		}
+	}
+}
+
+impl Default for Goals {
+	fn default() -> Goals {
+		Goals::new()
+	}
+}
+
+/// Signed turn, in (-π, π], to go from `heading` to heading towards (dx, dy)
+pub fn turn_towards(heading: f64, dx: f64, dy: f64) -> f64 {
+	Angle::from_vector(dx, dy).difference(Angle::new(heading))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn picks_the_closest_attractor_within_tolerance() {
+		let mut goals = Goals::new();
+		let far = goals.add(100.0, 100.0, 1.0, 300.0);
+		let near = goals.add(108.0, 100.0, -1.0, 300.0);
+		assert_eq!(goals.find_at(106.0, 100.0, 12.0), Some(near));
+		assert_eq!(goals.find_at(95.0, 100.0, 12.0), Some(far));
+		assert_eq!(goals.find_at(200.0, 100.0, 12.0), None);
+		assert!(goals.remove(near));
+		assert_eq!(goals.find_at(106.0, 100.0, 12.0), Some(far));
+	}
+}
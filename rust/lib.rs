@@ -1,13 +1,34 @@
 use wasm_bindgen::prelude::*;
-mod universe;
+pub mod angle;
+pub mod arena;
+pub mod circular;
+pub mod cluster;
+pub mod color;
+pub mod error;
+pub mod event;
+pub mod flock;
+pub mod flow;
+pub mod goal;
+pub mod mask;
+pub mod metrics;
+pub mod profile;
+pub mod registry;
+pub mod render;
+pub mod reynolds;
+pub mod rng;
+pub mod rules;
+pub mod space;
+pub mod universe;
+pub mod vec2;
 
 use std::cell::RefCell;
+use error::{Error, ErrorCode};
+use event::Event;
+use registry::Registry;
 
-// When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
-// allocator.
-#[cfg(feature = "wee_alloc")]
-#[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+// With the `parallel` feature, JS must await `initThreadPool(threads)` before the first tick
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
 
 #[wasm_bindgen]
 extern "C" {
@@ -22,6 +43,9 @@ extern "C" {
 
 	#[wasm_bindgen(js_namespace = console, js_name = log)]
 	fn log_f64_f64(a: f64, b: f64);
+
+	#[wasm_bindgen(js_namespace = console, js_name = error)]
+	fn log_error(error: &JsValue);
 }
 
 #[wasm_bindgen]
@@ -29,76 +53,492 @@ pub fn console_log(s: &str) {
 	log(s);
 }
 
-#[wasm_bindgen(module = "/js/interop.js")]
-extern "C" {
-	#[wasm_bindgen(js_name = sendMessage)]
-	fn send_message(s: &str);
-	
-	#[wasm_bindgen(js_name = sendMessage)]
-	fn send_key_value(s: &str, v: f64);
-}
-
 thread_local! {
-	pub static UNIVERSE: RefCell<Option<universe::Universe>> = RefCell::new(None);
+	/// every universe of the page, one per canvas, and the one the other exports act on
+	static REGISTRY: RefCell<Registry> = RefCell::new(Registry::new());
+	static LISTENER: RefCell<Option<js_sys::Function>> = RefCell::new(None);
 }
 
+/// `callback` is called with every `Event` as a plain object, see /rust/event.rs, with the handle
+/// of the current universe as its `universe` field. `undefined` stops the events
 #[wasm_bindgen]
-pub fn send_context(ctx: web_sys::CanvasRenderingContext2d, width: f64, height: f64) {
-	UNIVERSE.with(|universe| {
-		*universe.borrow_mut() = Some(universe::Universe::new(ctx, width, height));
+pub fn set_event_listener(callback: Option<js_sys::Function>) {
+	LISTENER.with(|listener| *listener.borrow_mut() = callback);
+}
+
+fn emit(event: Event) {
+	let handle = REGISTRY.with(|registry| registry.borrow().current());
+	LISTENER.with(|listener| {
+		if let Some(callback) = listener.borrow().as_ref() {
+			let event = JsValue::from(event);
+			let _ = js_sys::Reflect::set(&event, &JsValue::from_str("universe"), &handle.into());
+			if let Err(error) = callback.call1(&JsValue::NULL, &event) {
+				log_error(&error);
+			}
+		}
 	});
-	send_message("coucou interop");
 }
 
+/// Tells the listener about `error` before it is thrown
+fn fail(error: Error) -> JsValue {
+	emit(Event::Error(error.clone()));
+	error.into()
+}
+
+/// Runs `f` on the current universe. Errors are thrown in JS as an `Error` with an `ErrorCode` as its `code`
+fn with_universe<T>(f: impl FnOnce(&mut universe::Universe) -> Result<T, Error>) -> Result<T, JsValue> {
+	REGISTRY.with(|registry| f(registry.borrow_mut().current_mut()?)).map_err(fail)
+}
+
+/// New universe drawn on `ctx`, with its own flock and settings. It becomes the current universe,
+/// the one every other export acts on until `select_universe`. Returns its handle
 #[wasm_bindgen]
-pub fn get_boids_count() -> u32 {
-	UNIVERSE.with(|universe| {
-		universe.borrow().as_ref().unwrap().boids.len() as u32
-	})
+pub fn send_context(ctx: web_sys::CanvasRenderingContext2d, width: f64, height: f64) -> Result<u32, JsValue> {
+	error::dimensions(width, height).map_err(fail)?;
+	let seed = (js_sys::Math::random() * u32::MAX as f64) as u64;
+	let universe = universe::Universe::new(Some(ctx), width, height, seed);
+	Ok(REGISTRY.with(|registry| registry.borrow_mut().insert(universe)))
+}
+
+/// Makes the universe created by `send_context` with this handle the current one
+#[wasm_bindgen]
+pub fn select_universe(handle: u32) -> Result<(), JsValue> {
+	REGISTRY.with(|registry| registry.borrow_mut().select(handle)).map_err(fail)
+}
+
+/// Frees a universe. If it was the current one, there is none until `select_universe`
+#[wasm_bindgen]
+pub fn drop_universe(handle: u32) -> Result<(), JsValue> {
+	REGISTRY.with(|registry| registry.borrow_mut().remove(handle)).map_err(fail)
+}
+
+#[wasm_bindgen]
+pub fn get_boids_count() -> Result<u32, JsValue> {
+	with_universe(|universe| Ok(universe.boids_count() as u32))
 }
 
 #[wasm_bindgen]
-pub fn add_one_boid(x: f64, y: f64) -> u32 {
-	UNIVERSE.with(|universe| {
-		let mut option = universe.borrow_mut();
-		let universe = option.as_mut().unwrap();
-		universe.add_one_boid_xy(x, y);
-		universe.boids.len() as u32
+pub fn add_one_boid(x: f64, y: f64) -> Result<u32, JsValue> {
+	with_universe(|universe| {
+		universe.add_one_boid_xy(error::finite("x", x)?, error::finite("y", y)?);
+		Ok(universe.boids_count() as u32)
+	}).inspect(|&count| emit(Event::BoidAdded { count }))
+}
+
+/// `count` more boids at random open points of the 2D flock, e.g. to measure frame times at scale.
+/// Returns the number of boids of the 2D flock, even while the 3D one is shown
+#[wasm_bindgen]
+pub fn add_boids(count: u32) -> Result<u32, JsValue> {
+	with_universe(|universe| {
+		universe.add_boids(count as usize);
+		Ok(universe.flock.len() as u32)
+	}).inspect(|&count| emit(Event::BoidAdded { count }))
+}
+
+/// Removes the boid under the mouse, in 2D or 3D. Returns the number of boids left,
+/// or `undefined` when there was none, which is no error: clicks often miss
+#[wasm_bindgen]
+pub fn remove_boid(x: f64, y: f64) -> Result<Option<u32>, JsValue> {
+	with_universe(|universe| {
+		let removed = universe.remove_boid_at(error::finite("x", x)?, error::finite("y", y)?, 12.0);
+		Ok(removed.then(|| universe.boids_count() as u32))
+	}).inspect(|removed| if let Some(count) = *removed {
+		emit(Event::BoidRemoved { count })
 	})
 }
 
 #[wasm_bindgen]
-pub fn set_canvas_dimensions(width: f64, height: f64) {
-	UNIVERSE.with(|universe| {
-		let mut option = universe.borrow_mut();
-		let universe = option.as_mut().unwrap();
+pub fn set_canvas_dimensions(width: f64, height: f64) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		error::dimensions(width, height)?;
 		universe.canvas.width = width;
 		universe.canvas.height = height;
+		if let Some(space) = &mut universe.space {
+			space.resize(width, height);
+		}
+		Ok(())
+	})
+}
+
+/// Whether to simulate and draw a 3D flock in a box instead of the 2D one
+#[wasm_bindgen]
+pub fn set_space(enabled: bool) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.set_space(enabled);
+		Ok(())
+	})
+}
+
+#[wasm_bindgen]
+pub fn set_color_mode(mode: &str) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		let mode = color::ColorMode::from_name(mode).ok_or_else(|| Error::unknown_name("colour mode", mode))?;
+		universe.color_mode = mode;
+		if mode == color::ColorMode::Cluster && universe.clustering.is_none() {
+			universe.clustering = Some(cluster::Clustering::new(cluster::Linkage::Visibility));
+		}
+		Ok(())
+	})
+}
+
+/// "mean", "weighted" or "median"
+#[wasm_bindgen]
+pub fn set_alignment(alignment: &str) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.settings.alignment = universe::boid::Alignment::from_name(alignment)
+			.ok_or_else(|| Error::unknown_name("alignment", alignment))?;
+		Ok(())
+	})
+}
+
+/// "metric" for every boid in the vision cone, or the number of nearest boids in the field of view
+#[wasm_bindgen]
+pub fn set_neighbourhood(neighbourhood: &str) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.settings.neighbourhood = universe::boid::Neighbourhood::from_name(neighbourhood)
+			.ok_or_else(|| Error::unknown_name("neighbourhood", neighbourhood))?;
+		Ok(())
+	})
+}
+
+/// Whether boids see through each other and through obstacles
+#[wasm_bindgen]
+pub fn set_occlusion(occlusion: bool) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.settings.occlusion = occlusion;
+		Ok(())
+	})
+}
+
+/// "angular" or "reynolds"
+#[wasm_bindgen]
+pub fn set_steering(steering: &str) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.settings.steering = universe::boid::Steering::from_name(steering)
+			.ok_or_else(|| Error::unknown_name("steering", steering))?;
+		Ok(())
+	})
+}
+
+/// Weight of one of the steering rules, by name (`avoid_walls`, `avoid_entities`, `follow_group`, `go_to_group`, `seek_goals`)
+#[wasm_bindgen]
+pub fn set_rule_weight(name: &str, weight: f64) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		if universe.rules.set_weight(name, error::finite("weight", weight)?) {
+			Ok(())
+		} else {
+			Err(Error::not_found("rule", name))
+		}
+	})
+}
+
+/// Attractor if `strength` is positive, repeller if negative. Returns its id
+#[wasm_bindgen]
+pub fn add_attractor(x: f64, y: f64, strength: f64, radius: f64) -> Result<u32, JsValue> {
+	with_universe(|universe| {
+		error::all_finite("attractor", &[x, y, strength, radius])?;
+		Ok(universe.goals.add(x, y, strength, radius))
+	})
+}
+
+#[wasm_bindgen]
+pub fn move_attractor(id: u32, x: f64, y: f64) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		if universe.goals.move_to(id, error::finite("x", x)?, error::finite("y", y)?) {
+			Ok(())
+		} else {
+			Err(Error::not_found("attractor", id))
+		}
+	})
+}
+
+#[wasm_bindgen]
+pub fn remove_attractor(id: u32) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		if universe.goals.remove(id) {
+			Ok(())
+		} else {
+			Err(Error::not_found("attractor", id))
+		}
+	})
+}
+
+/// Id of the attractor under the mouse, if any
+#[wasm_bindgen]
+pub fn find_attractor(x: f64, y: f64) -> Result<Option<u32>, JsValue> {
+	with_universe(|universe| Ok(universe.goals.find_at(error::finite("x", x)?, error::finite("y", y)?, 12.0)))
+}
+
+#[wasm_bindgen]
+pub fn add_waypoint(x: f64, y: f64) -> Result<u32, JsValue> {
+	with_universe(|universe| {
+		universe.goals.waypoints.push((error::finite("x", x)?, error::finite("y", y)?));
+		Ok(universe.goals.waypoints.len() as u32)
+	})
+}
+
+#[wasm_bindgen]
+pub fn clear_waypoints() -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.goals.waypoints.clear();
+		Ok(())
+	})
+}
+
+fn set_flow_field(field: flow::FlowField) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.flow.field = field;
+		Ok(())
 	})
 }
 
 #[wasm_bindgen]
-pub fn request_tick(delta_time: f64, debug: bool) {
-	UNIVERSE.with(|universe| {
-		let mut option = universe.borrow_mut();
-		let universe = option.as_mut().unwrap();
-		let frames = delta_time / 15.0;
+pub fn set_wind(x: f64, y: f64) -> Result<(), JsValue> {
+	error::all_finite("wind", &[x, y]).map_err(fail)?;
+	set_flow_field(flow::FlowField::Uniform { x, y })
+}
+
+/// `radius`, where the current is strongest, must be positive
+#[wasm_bindgen]
+pub fn set_vortex(x: f64, y: f64, strength: f64, radius: f64) -> Result<(), JsValue> {
+	error::all_finite("vortex", &[x, y, strength, radius]).map_err(fail)?;
+	if radius <= 0.0 {
+		return Err(fail(Error::new(ErrorCode::InvalidDimensions, format!("vortex of radius {}", radius))))
+	}
+	set_flow_field(flow::FlowField::Vortex { x, y, strength, radius })
+}
+
+/// `scale` is the inverse of the size of the features in pixels, `speed` how fast they evolve per frame
+#[wasm_bindgen]
+pub fn set_flow_noise(scale: f64, strength: f64, speed: f64, seed: u32) -> Result<(), JsValue> {
+	error::all_finite("noise", &[scale, strength, speed]).map_err(fail)?;
+	set_flow_field(flow::FlowField::Noise { scale, strength, speed, seed })
+}
+
+/// `vectors` holds `columns * rows` (x, y) pairs spread over the canvas, row by row
+#[wasm_bindgen]
+pub fn set_flow_grid(columns: usize, rows: usize, vectors: Vec<f64>) -> Result<(), JsValue> {
+	if columns == 0 || rows == 0 {
+		return Err(fail(Error::new(ErrorCode::InvalidDimensions, format!("{} by {} flow grid", columns, rows))))
+	}
+	error::length("vectors", vectors.len(), columns * rows * 2).map_err(fail)?;
+	error::all_finite("vectors", &vectors).map_err(fail)?;
+	let vectors = vectors.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect();
+	set_flow_field(flow::FlowField::Grid { columns, rows, vectors })
+}
+
+#[wasm_bindgen]
+pub fn clear_flow() -> Result<(), JsValue> {
+	set_flow_field(flow::FlowField::Still)
+}
+
+/// How much boids are carried by the flow, and how fast they turn to face it
+#[wasm_bindgen]
+pub fn set_flow_response(drift: f64, heading_bias: f64) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.flow.drift = error::finite("drift", drift)?;
+		universe.flow.heading_bias = error::finite("heading_bias", heading_bias)?;
+		Ok(())
+	})
+}
+
+/// Walls made of closed polygons: `points` holds (x, y) pairs of every polygon one after the other,
+/// and `lengths` the number of points of each polygon. Polygons inside others are holes.
+/// Polygons of fewer than 3 points are dropped, and at least one must remain.
+#[wasm_bindgen]
+pub fn set_arena(points: Vec<f64>, lengths: Vec<u32>) -> Result<(), JsValue> {
+	error::length("points", points.len(), lengths.iter().map(|length| *length as usize * 2).sum()).map_err(fail)?;
+	error::all_finite("points", &points).map_err(fail)?;
+	let mut pairs = points.chunks_exact(2).map(|pair| (pair[0], pair[1]));
+	let polygons = lengths
+		.iter()
+		.map(|length| arena::Polygon::new(pairs.by_ref().take(*length as usize).collect()))
+		.filter(|polygon| polygon.points.len() >= 3)
+		.collect::<Vec<_>>();
+	// boids would have nowhere to go
+	if polygons.is_empty() {
+		return Err(fail(Error::new(ErrorCode::InvalidDimensions, "arena without any polygon of 3 points or more")))
+	}
+	with_universe(|universe| {
+		universe.set_arena(Some(arena::Arena::new(polygons)));
+		Ok(())
+	})
+}
+
+/// Round tank in the middle of the canvas
+#[wasm_bindgen]
+pub fn set_circular_arena() -> Result<(), JsValue> {
+	with_universe(|universe| {
+		let (width, height) = (universe.canvas.width, universe.canvas.height);
+		let circle = arena::Polygon::circle(width / 2.0, height / 2.0, width.min(height) / 2.0 - universe.canvas.padding, 64);
+		universe.set_arena(Some(arena::Arena::new(vec![circle])));
+		Ok(())
+	})
+}
+
+#[wasm_bindgen]
+pub fn clear_arena() -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.set_arena(None);
+		Ok(())
+	})
+}
+
+/// Obstacles from an image stretched over the canvas, as the RGBA bytes of an `ImageData`.
+/// Pixels darker than `threshold` (0-255) are solid
+#[wasm_bindgen]
+pub fn set_mask(bytes: Vec<u8>, width: usize, height: usize, threshold: u8) -> Result<(), JsValue> {
+	error::length("bytes", bytes.len(), width * height * 4).map_err(fail)?;
+	let mask = mask::Mask::from_rgba(&bytes, width, height, threshold).map_err(fail)?;
+	with_universe(|universe| {
+		universe.set_mask(Some(mask));
+		Ok(())
+	})
+}
+
+#[wasm_bindgen]
+pub fn clear_mask() -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.set_mask(None);
+		Ok(())
+	})
+}
+
+/// "visibility", "distance", or anything else to turn flock detection off
+#[wasm_bindgen]
+pub fn set_clustering(linkage: &str) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.clustering = cluster::Linkage::from_name(linkage).map(cluster::Clustering::new);
+		Ok(())
+	})
+}
+
+/// Flat list of `[id, size, centroid x, centroid y, heading]` for each flock, largest first
+#[wasm_bindgen]
+pub fn get_clusters() -> Result<Vec<f64>, JsValue> {
+	with_universe(|universe| {
+		Ok(universe.clustering.as_ref().map_or(vec![], |clustering| {
+			clustering.clusters
+				.iter()
+				.flat_map(|cluster| vec![
+					cluster.id as f64,
+					cluster.size as f64,
+					cluster.centroid.0,
+					cluster.centroid.1,
+					cluster.heading
+				])
+				.collect()
+		}))
+	})
+}
+
+#[wasm_bindgen]
+pub fn get_metrics() -> Result<metrics::Metrics, JsValue> {
+	with_universe(|universe| Ok(universe.metrics))
+}
+
+/// Profiles ticks and frames, keeping statistics over the last `window` of each, see /rust/profile.rs.
+/// A `window` of 0 stops profiling
+#[wasm_bindgen]
+pub fn set_profiling(window: u32) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.profiler = (window > 0).then(|| profile::Profiler::new(window as usize));
+		Ok(())
+	})
+}
+
+/// `{section: {last, mean, max}}` for every section measured so far, `undefined` when not profiling
+#[wasm_bindgen]
+pub fn get_profile() -> Result<JsValue, JsValue> {
+	with_universe(|universe| {
+		let profiler = match &universe.profiler {
+			Some(profiler) => profiler,
+			None => return Ok(JsValue::UNDEFINED)
+		};
+		let sections = js_sys::Object::new();
+		for (name, rolling) in profiler.sections() {
+			let stats = js_sys::Object::new();
+			for (key, value) in &[("last", rolling.last()), ("mean", rolling.mean()), ("max", rolling.max())] {
+				let _ = js_sys::Reflect::set(&stats, &JsValue::from_str(key), &JsValue::from(*value));
+			}
+			let _ = js_sys::Reflect::set(&sections, &JsValue::from_str(name), &stats);
+		}
+		Ok(sections.into())
+	})
+}
+
+#[wasm_bindgen]
+pub fn request_tick(delta_time: f64, debug: bool) -> Result<(), JsValue> {
+	let metrics = with_universe(|universe| {
+		let frames = error::finite("delta_time", delta_time)? / 15.0;
 		universe.tick(frames, debug);
-	});
-	send_key_value("tick", delta_time);
+		// the 3D flock isn't measured
+		Ok(universe.space.is_none().then_some(universe.metrics))
+	})?;
+	// emitted once the universe is released, so that listeners can call back into it
+	emit(Event::Tick { delta_time });
+	if let Some(metrics) = metrics {
+		emit(Event::Metrics(metrics));
+	}
+	Ok(())
 }
 
 #[wasm_bindgen]
-pub fn request_frame(delta_time: f64, draw_field_of_view: bool, debug: bool) {
-	UNIVERSE.with(|universe| {
-		let mut option = universe.borrow_mut();
-		let universe = option.as_mut().unwrap();
+pub fn request_frame(delta_time: f64, draw_field_of_view: bool, debug: bool) -> Result<(), JsValue> {
+	with_universe(|universe| {
 		universe.render(draw_field_of_view, debug);
-	});
-	send_key_value("frame", delta_time);
+		Ok(())
+	})?;
+	emit(Event::Frame { delta_time });
+	Ok(())
+}
+
+/// Whether to draw boids of close colours with a single fill, instead of one fill per boid
+#[wasm_bindgen]
+pub fn set_batched(batched: bool) -> Result<(), JsValue> {
+	with_universe(|universe| {
+		universe.batched = batched;
+		Ok(())
+	})
+}
+
+/// Fills the render buffer with the boids as they are now, and returns how many triangles it holds.
+/// The pointers below are only valid until the next call
+#[wasm_bindgen]
+pub fn update_render_buffer() -> Result<u32, JsValue> {
+	with_universe(|universe| {
+		universe.fill_render_buffer();
+		Ok(universe.render_buffer.len() as u32)
+	})
+}
+
+/// Start of the triangles in wasm memory, `render::STRIDE` floats per boid:
+/// x0, y0, x1, y1, x2, y2, colour index
+#[wasm_bindgen]
+pub fn render_buffer_ptr() -> Result<*const f32, JsValue> {
+	with_universe(|universe| Ok(universe.render_buffer.triangles.as_ptr()))
+}
+
+/// Number of floats, not of triangles
+#[wasm_bindgen]
+pub fn render_buffer_len() -> Result<u32, JsValue> {
+	with_universe(|universe| Ok(universe.render_buffer.triangles.len() as u32))
+}
+
+/// Start of the colours as 0xRRGGBBAA, indexed by the last float of each triangle
+#[wasm_bindgen]
+pub fn render_colors_ptr() -> Result<*const u32, JsValue> {
+	with_universe(|universe| Ok(universe.render_buffer.colors.as_ptr()))
+}
+
+#[wasm_bindgen]
+pub fn render_colors_len() -> Result<u32, JsValue> {
+	with_universe(|universe| Ok(universe.render_buffer.colors.len() as u32))
 }
 
 #[wasm_bindgen(start)]
 pub fn main() {
+	// panics print their message to the console instead of a bare "unreachable"
+	console_error_panic_hook::set_once();
 	log("alive !!");
 }
@@ -0,0 +1,242 @@
+use crate::arena::Hit;
+use crate::error::{Error, ErrorCode};
+use crate::universe::boid::Canvas;
+
+/// Obstacles painted in an image stretched over the canvas: dark pixels are solid
+pub struct Mask {
+	width: usize,
+	height: usize,
+	solid: Vec<bool>,
+	/// distance from each pixel to the closest solid pixel, in pixels of the mask
+	distance: Vec<f64>
+}
+
+impl Mask {
+	/// One byte per pixel, row by row. Pixels darker than `threshold` are solid
+	pub fn from_grayscale(bytes: &[u8], width: usize, height: usize, threshold: u8) -> Result<Mask, Error> {
+		let solid = (0..width * height).map(|i| bytes.get(i).is_some_and(|value| *value < threshold)).collect();
+		Mask::from_solid(solid, width, height)
+	}
+
+	/// Four bytes per pixel, like `ImageData`. Transparent pixels are never solid
+	pub fn from_rgba(bytes: &[u8], width: usize, height: usize, threshold: u8) -> Result<Mask, Error> {
+		let solid = (0..width * height)
+			.map(|i| match bytes.get(i * 4..i * 4 + 4) {
+				Some([r, g, b, a]) => *a > 127 && luminance(*r, *g, *b) < threshold as f64,
+				_ => false
+			})
+			.collect();
+		Mask::from_solid(solid, width, height)
+	}
+
+	/// `ErrorCode::InvalidDimensions` for an image without pixels, which couldn't be stretched over the canvas
+	fn from_solid(solid: Vec<bool>, width: usize, height: usize) -> Result<Mask, Error> {
+		if width == 0 || height == 0 {
+			return Err(Error::new(ErrorCode::InvalidDimensions, format!("{} by {} mask", width, height)))
+		}
+		let distance = distance_field(&solid, width, height);
+		Ok(Mask {
+			width,
+			height,
+			solid,
+			distance
+		})
+	}
+
+	fn pixel(&self, x: f64, y: f64, canvas: &Canvas) -> Option<usize> {
+		let column = (x / canvas.width * self.width as f64).floor();
+		let row = (y / canvas.height * self.height as f64).floor();
+		if column < 0.0 || row < 0.0 || column >= self.width as f64 || row >= self.height as f64 {
+			None
+		} else {
+			Some(row as usize * self.width + column as usize)
+		}
+	}
+
+	/// Outside of the image counts as solid
+	pub fn is_solid(&self, x: f64, y: f64, canvas: &Canvas) -> bool {
+		self.pixel(x, y, canvas).is_none_or(|i| self.solid[i])
+	}
+
+	/// Distance to the closest solid pixel, in canvas pixels (approximate when the mask is stretched unevenly)
+	pub fn distance(&self, x: f64, y: f64, canvas: &Canvas) -> f64 {
+		let scale = (canvas.width / self.width as f64).min(canvas.height / self.height as f64);
+		self.pixel(x, y, canvas).map_or(0.0, |i| self.distance[i] * scale)
+	}
+
+	/// Direction in which the distance to obstacles grows the fastest
+	pub fn gradient(&self, x: f64, y: f64, canvas: &Canvas) -> (f64, f64) {
+		let step = (canvas.width / self.width as f64).max(canvas.height / self.height as f64);
+		let dx = self.distance(x + step, y, canvas) - self.distance(x - step, y, canvas);
+		let dy = self.distance(x, y + step, canvas) - self.distance(x, y - step, canvas);
+		let norm = (dx.powi(2) + dy.powi(2)).sqrt();
+		if norm == 0.0 {
+			(0.0, 0.0)
+		} else {
+			(dx / norm, dy / norm)
+		}
+	}
+
+	/// Sphere tracing through the distance field, from (x, y) along the unit vector `direction`
+	pub fn cast(&self, x: f64, y: f64, direction: (f64, f64), length: f64, canvas: &Canvas) -> Option<Hit> {
+		let margin = (canvas.width / self.width as f64).max(canvas.height / self.height as f64);
+		let mut traveled = 0.0;
+		while traveled <= length {
+			let point = (x + direction.0 * traveled, y + direction.1 * traveled);
+			let distance = self.distance(point.0, point.1, canvas);
+			if distance <= margin {
+				let mut normal = self.gradient(point.0, point.1, canvas);
+				if normal == (0.0, 0.0) {
+					normal = (- direction.0, - direction.1);
+				}
+				return Some(Hit {
+					distance: traveled,
+					tangent: (- normal.1, normal.0),
+					normal
+				})
+			}
+			traveled += distance - margin / 2.0;
+		}
+		None
+	}
+}
+
+fn luminance(r: u8, g: u8, b: u8) -> f64 {
+	0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
+}
+
+/// Exact euclidean distance transform (Felzenszwalb & Huttenlocher), one dimension at a time
+fn distance_field(solid: &[bool], width: usize, height: usize) -> Vec<f64> {
+	let infinity = ((width + height) as f64).powi(2);
+	let mut squared: Vec<f64> = solid.iter().map(|solid| if *solid { 0.0 } else { infinity }).collect();
+
+	let mut column = vec![0.0; height];
+	for x in 0..width {
+		for y in 0..height {
+			column[y] = squared[y * width + x];
+		}
+		let transformed = distance_1d(&column);
+		for y in 0..height {
+			squared[y * width + x] = transformed[y];
+		}
+	}
+	for y in 0..height {
+		let transformed = distance_1d(&squared[y * width..(y + 1) * width]);
+		squared[y * width..(y + 1) * width].copy_from_slice(&transformed);
+	}
+
+	squared.iter().map(|value| value.sqrt()).collect()
+}
+
+/// Lower envelope of the parabolas rooted at each sample
+fn distance_1d(f: &[f64]) -> Vec<f64> {
+	let n = f.len();
+	let mut result = vec![0.0; n];
+	if n == 0 {
+		return result
+	}
+	let mut vertices = vec![0usize; n];
+	let mut boundaries = vec![0.0; n + 1];
+	let mut k = 0;
+	boundaries[0] = f64::NEG_INFINITY;
+	boundaries[1] = f64::INFINITY;
+	let intersection = |q: usize, v: usize| ((f[q] + (q * q) as f64) - (f[v] + (v * v) as f64)) / (2.0 * q as f64 - 2.0 * v as f64);
+	for q in 1..n {
+		let mut s = intersection(q, vertices[k]);
+		// boundaries[0] is -∞ so this stops at k == 0
+		while s <= boundaries[k] {
+			k -= 1;
+			s = intersection(q, vertices[k]);
+		}
+		k += 1;
+		vertices[k] = q;
+		boundaries[k] = s;
+		boundaries[k + 1] = f64::INFINITY;
+	}
+	k = 0;
+	for (q, value) in result.iter_mut().enumerate() {
+		while boundaries[k + 1] < q as f64 {
+			k += 1;
+		}
+		let v = vertices[k];
+		*value = (q as f64 - v as f64).powi(2) + f[v];
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Distance from every pixel to the closest solid one, by trying them all
+	fn brute_force(solid: &[bool], width: usize, height: usize) -> Vec<f64> {
+		(0..width * height)
+			.map(|i| {
+				let (x, y) = ((i % width) as f64, (i / width) as f64);
+				(0..width * height)
+					.filter(|j| solid[*j])
+					.map(|j| (((j % width) as f64 - x).powi(2) + ((j / width) as f64 - y).powi(2)).sqrt())
+					.fold(f64::INFINITY, f64::min)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn distance_transform_is_exact() {
+		let (width, height) = (13, 7);
+		let mut seed = 1u32;
+		let solid: Vec<bool> = (0..width * height)
+			.map(|_| {
+				seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+				(seed >> 16).is_multiple_of(9)
+			})
+			.collect();
+		assert!(solid.iter().any(|solid| *solid));
+		let field = distance_field(&solid, width, height);
+		for (i, (actual, expected)) in field.iter().zip(brute_force(&solid, width, height)).enumerate() {
+			assert!((actual - expected).abs() < 1e-9, "pixel {}: {} instead of {}", i, actual, expected);
+		}
+	}
+
+	#[test]
+	fn distance_transform_of_a_single_pixel() {
+		let mut solid = vec![false; 25];
+		solid[12] = true;
+		let field = distance_field(&solid, 5, 5);
+		assert_eq!(field[12], 0.0);
+		assert_eq!(field[13], 1.0);
+		assert_eq!(field[2], 2.0);
+		assert!((field[0] - 8.0f64.sqrt()).abs() < 1e-9);
+	}
+
+	#[test]
+	fn distance_transform_along_a_line() {
+		assert_eq!(distance_1d(&[]), Vec::<f64>::new());
+		let infinity = 100.0;
+		assert_eq!(distance_1d(&[infinity, 0.0, infinity, infinity, 0.0]), vec![1.0, 0.0, 1.0, 1.0, 0.0]);
+	}
+
+	#[test]
+	fn masks_need_pixels() {
+		for (width, height) in [(0, 4), (4, 0), (0, 0)] {
+			let error = Mask::from_rgba(&[], width, height, 128).err().unwrap();
+			assert_eq!(error.code, ErrorCode::InvalidDimensions);
+			assert!(Mask::from_grayscale(&[], width, height, 128).is_err());
+		}
+	}
+
+	#[test]
+	fn dark_opaque_pixels_are_solid() {
+		let canvas = Canvas { width: 20.0, height: 10.0, padding: 0.0, arena: None, mask: None };
+		// black, white, transparent black, then dark gray
+		let bytes = [0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 0, 60, 60, 60, 255];
+		let mask = Mask::from_rgba(&bytes, 2, 2, 128).unwrap();
+		assert!(mask.is_solid(5.0, 2.0, &canvas));
+		assert!(!mask.is_solid(15.0, 2.0, &canvas));
+		assert!(!mask.is_solid(5.0, 8.0, &canvas));
+		assert!(mask.is_solid(15.0, 8.0, &canvas));
+		// outside of the image
+		assert!(mask.is_solid(- 1.0, 2.0, &canvas));
+		assert!(mask.is_solid(5.0, 10.0, &canvas));
+	}
+}
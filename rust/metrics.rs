@@ -0,0 +1,138 @@
+use wasm_bindgen::prelude::*;
+use crate::flock::Flock;
+use crate::universe::boid::Boid;
+
+/// Collective-motion order parameters of the whole flock
+#[wasm_bindgen]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Metrics {
+	/// norm of the mean heading, 1 when every boid flies the same way
+	pub polarization: f64,
+	/// norm of the mean angular momentum around the centroid, 1 when milling in a perfect circle
+	pub milling: f64,
+	/// mean distance to the nearest other boid, among boids that have one within vision range
+	pub nearest_neighbour_distance: f64,
+	/// mean number of boids seen by each boid
+	pub average_neighbours: f64
+}
+
+impl Metrics {
+	/// `candidates` must return, for a given boid, every other boid within vision range
+	/// (typically its cell of `grid_split`). Boids out of range don't count towards
+	/// the nearest neighbour distance. `neighbours` holds the number of boids each boid sees
+	/// from where the flock is now, by index, as counted by the `Boid::update` of this tick:
+	/// `Flock::neighbours` still holds the counts of the previous one.
+	pub fn measure<'a, F>(flock: &Flock, candidates: F, neighbours: &[usize]) -> Metrics
+	where F: Fn(&Boid) -> &'a [Boid<'a>] {
+		let count = flock.len() as f64;
+		if flock.is_empty() {
+			return Metrics::default()
+		}
+
+		let sum_x: f64 = flock.x.iter().sum();
+		let sum_y: f64 = flock.y.iter().sum();
+		let (center_x, center_y) = (sum_x / count, sum_y / count);
+
+		let mut heading_x = 0.0;
+		let mut heading_y = 0.0;
+		let mut momentum = 0.0;
+		let mut nearest_sum = 0.0;
+		let mut nearest_count = 0;
+
+		for boid in flock.iter() {
+			let (dx, dy) = heading_vector(&boid);
+			heading_x += dx;
+			heading_y += dy;
+
+			let rx = boid.x() - center_x;
+			let ry = boid.y() - center_y;
+			let radius = (rx.powi(2) + ry.powi(2)).sqrt();
+			if radius > 0.0 {
+				momentum += (rx * dy - ry * dx) / radius;
+			}
+
+			let nearest = candidates(&boid)
+				.iter()
+				.filter(|other| other.index() != boid.index())
+				.map(|other| (other.x() - boid.x()).powi(2) + (other.y() - boid.y()).powi(2))
+				.filter(|squared_distance| *squared_distance <= boid.vision().radius.powi(2))
+				.fold(f64::INFINITY, f64::min)
+				.sqrt();
+			if nearest.is_finite() {
+				nearest_sum += nearest;
+				nearest_count += 1;
+			}
+		}
+
+		Metrics {
+			polarization: (heading_x.powi(2) + heading_y.powi(2)).sqrt() / count,
+			milling: momentum.abs() / count,
+			nearest_neighbour_distance: if nearest_count > 0 { nearest_sum / nearest_count as f64 } else { 0.0 },
+			average_neighbours: neighbours.iter().sum::<usize>() as f64 / count
+		}
+	}
+}
+
+/// Unit vector in the direction the boid is moving
+fn heading_vector(boid: &Boid) -> (f64, f64) {
+	(- boid.heading().sin(), - boid.heading().cos())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::f64::consts::PI;
+	use crate::rng::Rng;
+	use crate::universe::boid::{Canvas, Point};
+
+	/// Boids at `points`, each with the heading that follows it
+	fn flock(points: &[((f64, f64), f64)]) -> Flock {
+		let canvas = Canvas { width: 1000.0, height: 1000.0, padding: 0.0, arena: None, mask: None };
+		let mut rng = Rng::new(0);
+		let mut flock = Flock::new();
+		for &((x, y), angle) in points {
+			let index = flock.spawn(Some(Point { x, y }), &canvas, &mut rng);
+			flock.angle[index] = angle;
+		}
+		flock
+	}
+
+	fn nobody<'a>(_: &Boid) -> &'a [Boid<'a>] {
+		&[]
+	}
+
+	#[test]
+	fn aligned_flock_is_fully_polarized() {
+		let points: Vec<_> = (0..10).map(|i| ((100.0 + i as f64 * 37.0, 500.0 - i as f64 * 11.0), 1.2)).collect();
+		let metrics = Metrics::measure(&flock(&points), nobody, &[0; 10]);
+		assert!((metrics.polarization - 1.0).abs() < 1e-12);
+	}
+
+	#[test]
+	fn ring_mills_without_polarization() {
+		// moving along the tangent of the ring, all the same way round
+		let points: Vec<_> = (0..12)
+			.map(|i| {
+				let theta = PI * 2.0 * i as f64 / 12.0;
+				((500.0 + 200.0 * theta.cos(), 500.0 + 200.0 * theta.sin()), PI - theta)
+			})
+			.collect();
+		let metrics = Metrics::measure(&flock(&points), nobody, &[0; 12]);
+		assert!((metrics.milling - 1.0).abs() < 1e-12);
+		assert!(metrics.polarization < 1e-12);
+	}
+
+	#[test]
+	fn neighbours_are_averaged_from_this_tick() {
+		let metrics = Metrics::measure(&flock(&[((0.0, 0.0), 0.0), ((10.0, 0.0), 0.0)]), nobody, &[1, 4]);
+		assert_eq!(metrics.average_neighbours, 2.5);
+		assert_eq!(metrics.nearest_neighbour_distance, 0.0);
+	}
+
+	#[test]
+	fn empty_flock_measures_zero() {
+		let metrics = Metrics::measure(&Flock::new(), nobody, &[]);
+		assert_eq!(metrics.polarization, 0.0);
+		assert_eq!(metrics.average_neighbours, 0.0);
+	}
+}
@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+	// `performance` exists in windows and workers alike
+	#[wasm_bindgen(js_namespace = performance, js_name = now)]
+	fn performance_now() -> f64;
+}
+
+/// Milliseconds since an arbitrary origin, for differences only
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+	performance_now()
+}
+
+/// Milliseconds since an arbitrary origin, for differences only
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+	use std::sync::OnceLock;
+	use std::time::Instant;
+	static ORIGIN: OnceLock<Instant> = OnceLock::new();
+	ORIGIN.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+/// Filled by the boid updates of a tick, which may run on several threads
+#[derive(Default, Debug)]
+pub struct Counters {
+	/// boids handed to `Boid::update` as possible neighbours
+	pub candidates: AtomicU64,
+	/// boids that passed the visibility filters
+	pub visible: AtomicU64
+}
+
+impl Counters {
+	pub fn add(counter: &AtomicU64, value: u64) {
+		counter.fetch_add(value, Ordering::Relaxed);
+	}
+
+	pub fn get(counter: &AtomicU64) -> u64 {
+		counter.load(Ordering::Relaxed)
+	}
+}
+
+/// Last `window` values of a section
+#[derive(Clone, Debug)]
+pub struct Rolling {
+	values: VecDeque<f64>,
+	window: usize
+}
+
+impl Rolling {
+	pub fn new(window: usize) -> Rolling {
+		Rolling { values: VecDeque::with_capacity(window), window }
+	}
+
+	pub fn push(&mut self, value: f64) {
+		if self.values.len() == self.window {
+			self.values.pop_front();
+		}
+		self.values.push_back(value);
+	}
+
+	pub fn last(&self) -> f64 {
+		self.values.back().copied().unwrap_or(0.0)
+	}
+
+	pub fn mean(&self) -> f64 {
+		if self.values.is_empty() {
+			return 0.0
+		}
+		self.values.iter().sum::<f64>() / self.values.len() as f64
+	}
+
+	pub fn max(&self) -> f64 {
+		self.values.iter().copied().fold(0.0, f64::max)
+	}
+}
+
+/// Opt-in measurements of `Universe::tick` and `Universe::render`, kept over the last `window` of each.
+/// Sections ending in `_ms` are durations in milliseconds:
+/// - `tick_ms`, `grid_split_ms`, `update_ms` for every `Boid::update` together, `metrics_ms` for metrics and clustering
+/// - `rule_ms:<name>` for each rule of `Steering::Angular`, timed over the whole flock in a pass of its own
+///   after the updates, on a single thread. Neither `tick_ms` nor `update_ms` includes these passes
+/// - `render_ms`
+///
+/// and `candidates` and `visible` count the possible and the visible neighbours of every boid, per tick
+#[derive(Clone, Debug)]
+pub struct Profiler {
+	window: usize,
+	/// in order of first record
+	sections: Vec<(String, Rolling)>
+}
+
+impl Profiler {
+	pub fn new(window: usize) -> Profiler {
+		Profiler { window: window.max(1), sections: vec![] }
+	}
+
+	pub fn record(&mut self, section: &str, value: f64) {
+		let index = match self.sections.iter().position(|(name, _)| name == section) {
+			Some(index) => index,
+			None => {
+				self.sections.push((section.to_string(), Rolling::new(self.window)));
+				self.sections.len() - 1
+			}
+		};
+		self.sections[index].1.push(value);
+	}
+
+	/// Records the time elapsed since `start`, from `now`
+	pub fn record_since(&mut self, section: &str, start: f64) {
+		self.record(section, now() - start);
+	}
+
+	pub fn get(&self, section: &str) -> Option<&Rolling> {
+		self.sections.iter().find(|(name, _)| name == section).map(|(_, rolling)| rolling)
+	}
+
+	pub fn sections(&self) -> impl Iterator<Item = (&str, &Rolling)> {
+		self.sections.iter().map(|(name, rolling)| (name.as_str(), rolling))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rolling_statistics_forget_old_values() {
+		let mut rolling = Rolling::new(3);
+		assert_eq!((rolling.last(), rolling.mean(), rolling.max()), (0.0, 0.0, 0.0));
+		for value in &[10.0, 1.0, 2.0, 3.0] {
+			rolling.push(*value);
+		}
+		assert_eq!((rolling.last(), rolling.mean(), rolling.max()), (3.0, 2.0, 3.0));
+	}
+
+	#[test]
+	fn sections_keep_the_order_they_were_first_recorded_in() {
+		let mut profiler = Profiler::new(10);
+		profiler.record("b", 1.0);
+		profiler.record("a", 2.0);
+		profiler.record("b", 3.0);
+		let names: Vec<&str> = profiler.sections().map(|(name, _)| name).collect();
+		assert_eq!(names, vec!["b", "a"]);
+		assert_eq!(profiler.get("b").unwrap().mean(), 2.0);
+		assert!(profiler.get("c").is_none());
+	}
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use crate::error::{Error, ErrorCode};
+use crate::universe::Universe;
+
+/// Every universe of the page by handle, one per canvas, and the one the exports act on.
+/// Handles start at 1 and are never reused
+pub struct Registry {
+	universes: HashMap<u32, Universe>,
+	current: Option<u32>,
+	next_handle: u32
+}
+
+impl Registry {
+	pub fn new() -> Registry {
+		Registry {
+			universes: HashMap::new(),
+			current: None,
+			next_handle: 0
+		}
+	}
+
+	/// Stores `universe`, makes it the current one, and returns its handle
+	pub fn insert(&mut self, universe: Universe) -> u32 {
+		self.next_handle += 1;
+		let handle = self.next_handle;
+		self.universes.insert(handle, universe);
+		self.current = Some(handle);
+		handle
+	}
+
+	pub fn select(&mut self, handle: u32) -> Result<(), Error> {
+		if self.universes.contains_key(&handle) {
+			self.current = Some(handle);
+			Ok(())
+		} else {
+			Err(Error::not_found("universe", handle))
+		}
+	}
+
+	/// Drops a universe. If it was the current one, there is none until `select`
+	pub fn remove(&mut self, handle: u32) -> Result<(), Error> {
+		self.universes.remove(&handle).ok_or_else(|| Error::not_found("universe", handle))?;
+		if self.current == Some(handle) {
+			self.current = None;
+		}
+		Ok(())
+	}
+
+	pub fn current(&self) -> Option<u32> {
+		self.current
+	}
+
+	/// `ErrorCode::NotInitialised` when nothing was inserted yet, or the current universe was removed
+	pub fn current_mut(&mut self) -> Result<&mut Universe, Error> {
+		let current = self.current;
+		current
+			.and_then(move |handle| self.universes.get_mut(&handle))
+			.ok_or_else(|| Error::new(ErrorCode::NotInitialised, "send_context or select_universe must be called first"))
+	}
+}
+
+impl Default for Registry {
+	fn default() -> Registry {
+		Registry::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Universe told apart from the others by its width
+	fn universe(width: f64) -> Universe {
+		Universe::new(None, width, 100.0, 0)
+	}
+
+	fn current_width(registry: &mut Registry) -> f64 {
+		registry.current_mut().unwrap().canvas.width
+	}
+
+	#[test]
+	fn the_last_inserted_is_current() {
+		let mut registry = Registry::new();
+		assert_eq!(registry.current_mut().err().map(|error| error.code), Some(ErrorCode::NotInitialised));
+		let first = registry.insert(universe(1.0));
+		let second = registry.insert(universe(2.0));
+		assert_eq!((first, second), (1, 2));
+		assert_eq!(registry.current(), Some(second));
+		assert_eq!(current_width(&mut registry), 2.0);
+	}
+
+	#[test]
+	fn select_switches_between_existing_universes() {
+		let mut registry = Registry::new();
+		let first = registry.insert(universe(1.0));
+		registry.insert(universe(2.0));
+		registry.select(first).unwrap();
+		assert_eq!(current_width(&mut registry), 1.0);
+		assert_eq!(registry.select(7).unwrap_err().code, ErrorCode::NotFound);
+		assert_eq!(registry.current(), Some(first));
+	}
+
+	#[test]
+	fn removing_the_current_universe_leaves_none() {
+		let mut registry = Registry::new();
+		let first = registry.insert(universe(1.0));
+		let second = registry.insert(universe(2.0));
+		// another one stays current
+		registry.remove(first).unwrap();
+		assert_eq!(registry.current(), Some(second));
+		registry.remove(second).unwrap();
+		assert_eq!(registry.current(), None);
+		assert_eq!(registry.current_mut().err().map(|error| error.code), Some(ErrorCode::NotInitialised));
+		assert_eq!(registry.remove(second).unwrap_err().code, ErrorCode::NotFound);
+		assert_eq!(registry.select(second).unwrap_err().code, ErrorCode::NotFound);
+	}
+
+	#[test]
+	fn handles_are_not_reused() {
+		let mut registry = Registry::new();
+		let first = registry.insert(universe(1.0));
+		registry.remove(first).unwrap();
+		assert_eq!(registry.insert(universe(2.0)), first + 1);
+	}
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use crate::color::Rgba;
+use crate::vec2::Vec2;
+
+/// Bits per channel of the colour buckets of `RenderBuffer::draw`
+const BUCKET_BITS: u32 = 4;
+
+/// Floats per boid in `RenderBuffer::triangles`: the three (x, y) vertices of its triangle,
+/// then the index of its colour in `RenderBuffer::colors`
+pub const STRIDE: usize = 7;
+
+/// What `Universe::render` would draw, packed so that it can be read in place from wasm memory,
+/// as a `Float32Array` of triangles and a `Uint32Array` of colours
+#[derive(Default)]
+pub struct RenderBuffer {
+	pub triangles: Vec<f32>,
+	/// distinct colours of the frame as 0xRRGGBBAA, in order of first appearance
+	pub colors: Vec<u32>,
+	indices: HashMap<u32, usize>
+}
+
+impl RenderBuffer {
+	pub fn clear(&mut self) {
+		self.triangles.clear();
+		self.colors.clear();
+		self.indices.clear();
+	}
+
+	/// Number of triangles
+	pub fn len(&self) -> usize {
+		self.triangles.len() / STRIDE
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.triangles.is_empty()
+	}
+
+	/// Triangles are drawn in the order they are pushed
+	pub fn push(&mut self, triangle: [Vec2; 3], color: Rgba) {
+		let colors = &mut self.colors;
+		let index = *self.indices.entry(color.0).or_insert_with(|| {
+			colors.push(color.0);
+			colors.len() - 1
+		});
+		for vertex in &triangle {
+			self.triangles.extend_from_slice(&[vertex.x as f32, vertex.y as f32]);
+		}
+		self.triangles.push(index as f32);
+	}
+
+	/// One path and one fill per colour bucket, instead of a style change and a fill per triangle.
+	/// Close shades share a bucket. With `reorder`, triangles are grouped by bucket across the
+	/// whole buffer, otherwise only runs of consecutive triangles are, which keeps the drawing order
+	pub fn draw(&self, context: &web_sys::CanvasRenderingContext2d, reorder: bool) {
+		// a frame has far fewer colours than triangles
+		let buckets: Vec<Rgba> = self.colors.iter().map(|&color| Rgba(color).quantise(BUCKET_BITS)).collect();
+		let bucket = |triangle: usize| buckets[self.triangles[triangle * STRIDE + 6] as usize];
+		let mut order: Vec<usize> = (0..self.len()).collect();
+		if reorder {
+			order.sort_by_key(|&triangle| bucket(triangle).0);
+		}
+
+		// fill closes each triangle, and boids all turn the same way round theirs, so overlaps stay filled
+		let mut current = None;
+		for triangle in order {
+			let color = bucket(triangle);
+			if current != Some(color) {
+				if current.is_some() {
+					context.fill();
+				}
+				context.set_fill_style_str(&color.to_css());
+				context.begin_path();
+				current = Some(color);
+			}
+			let vertices = &self.triangles[triangle * STRIDE..triangle * STRIDE + 6];
+			context.move_to(vertices[0] as f64, vertices[1] as f64);
+			context.line_to(vertices[2] as f64, vertices[3] as f64);
+			context.line_to(vertices[4] as f64, vertices[5] as f64);
+		}
+		if current.is_some() {
+			context.fill();
+		}
+	}
+}
@@ -0,0 +1,144 @@
+use std::ops::{Add, Div, Mul, Sub};
+use crate::space::Vec3;
+use crate::universe::boid::Forces;
+use crate::vec2::Vec2;
+
+/// What the Reynolds forces need of a vector, so that the 2D flock and a `Space` share them
+pub trait Vector: Copy + PartialEq + Add<Output = Self> + Sub<Output = Self> + Mul<f64, Output = Self> + Div<f64, Output = Self> {
+	const ZERO: Self;
+	fn length(self) -> f64;
+	fn with_length(self, length: f64) -> Self;
+	fn with_max_length(self, length: f64) -> Self;
+}
+
+impl Vector for Vec2 {
+	const ZERO: Vec2 = Vec2::ZERO;
+
+	fn length(self) -> f64 {
+		Vec2::length(self)
+	}
+
+	fn with_length(self, length: f64) -> Vec2 {
+		Vec2::with_length(self, length)
+	}
+
+	fn with_max_length(self, length: f64) -> Vec2 {
+		Vec2::with_max_length(self, length)
+	}
+}
+
+impl Vector for Vec3 {
+	const ZERO: Vec3 = Vec3::ZERO;
+
+	fn length(self) -> f64 {
+		Vec3::length(self)
+	}
+
+	fn with_length(self, length: f64) -> Vec3 {
+		Vec3::with_length(self, length)
+	}
+
+	fn with_max_length(self, length: f64) -> Vec3 {
+		Vec3::with_max_length(self, length)
+	}
+}
+
+/// What the forces see of a boid
+#[derive(Clone, Copy, Debug)]
+pub struct Body<V> {
+	pub position: V,
+	pub velocity: V,
+	pub size: f64
+}
+
+/// Steering forces of a boid flying at `velocity`, towards `max_speed`
+pub struct Steer<'a, V> {
+	pub velocity: V,
+	pub max_speed: f64,
+	pub forces: &'a Forces
+}
+
+impl<V: Vector> Steer<'_, V> {
+	/// From the current velocity towards `desired` at full speed, at most `max_force` long, then weighted
+	pub fn towards(&self, desired: V, weight: f64) -> V {
+		let desired = desired.with_length(self.max_speed);
+		(desired - self.velocity).with_max_length(self.forces.max_force) * weight
+	}
+
+	/// Separation, alignment and cohesion of `boid` with the flockmates it sees
+	pub fn flocking(&self, boid: &Body<V>, visible: impl Iterator<Item = Body<V>> + Clone) -> V {
+		let mut acceleration = V::ZERO;
+
+		// separation: away from boids too close, more so the closer they are.
+		// Boids on the very same point can't tell which way is away
+		let away = visible
+			.clone()
+			.filter_map(|other| {
+				let offset = boid.position - other.position;
+				let distance = offset.length();
+				if distance > 0.0 && distance < boid.size + other.size {
+					Some(offset / distance.powi(2))
+				} else {
+					None
+				}
+			})
+			.fold(V::ZERO, |sum, away| sum + away);
+		if away != V::ZERO {
+			acceleration = acceleration + self.towards(away, self.forces.separation);
+		}
+
+		let count = visible.clone().count() as f64;
+		if count > 0.0 {
+			// alignment: same velocity as the average of visible boids
+			let heading = visible.clone().fold(V::ZERO, |sum, other| sum + other.velocity);
+			acceleration = acceleration + self.towards(heading, self.forces.alignment);
+
+			// cohesion: towards the center of visible boids
+			let center = visible.fold(V::ZERO, |sum, other| sum + other.position / count);
+			acceleration = acceleration + self.towards(center - boid.position, self.forces.cohesion);
+		}
+		acceleration
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FORCES: Forces = Forces { separation: 1.5, alignment: 1.0, cohesion: 1.0, avoid_walls: 2.0, max_force: 0.1 };
+
+	fn body(x: f64, y: f64, velocity_x: f64, velocity_y: f64) -> Body<Vec2> {
+		Body { position: Vec2::new(x, y), velocity: Vec2::new(velocity_x, velocity_y), size: 10.0 }
+	}
+
+	#[test]
+	fn forces_are_capped_then_weighted() {
+		let steer = Steer { velocity: Vec2::new(1.0, 0.0), max_speed: 2.0, forces: &FORCES };
+		// already flying that way at full speed
+		assert_eq!(steer.towards(Vec2::new(5.0, 0.0), 1.0), Vec2::new(0.1, 0.0));
+		assert_eq!(steer.towards(Vec2::new(0.0, 1.0), 2.0).length(), 0.2);
+	}
+
+	#[test]
+	fn alone_a_boid_keeps_its_course() {
+		let steer = Steer { velocity: Vec2::new(1.0, 0.0), max_speed: 2.0, forces: &FORCES };
+		assert_eq!(steer.flocking(&body(0.0, 0.0, 1.0, 0.0), std::iter::empty()), Vec2::ZERO);
+	}
+
+	#[test]
+	fn same_forces_in_the_plane_and_in_space() {
+		let boid = body(50.0, 50.0, 1.0, 0.5);
+		let others = [body(55.0, 52.0, 0.0, 1.0), body(80.0, 40.0, - 1.0, 0.0), body(50.0, 50.0, 1.0, 1.0)];
+		let flat = Steer { velocity: boid.velocity, max_speed: 3.0, forces: &FORCES }.flocking(&boid, others.iter().copied());
+
+		let lift = |body: &Body<Vec2>| Body {
+			position: Vec3::new(body.position.x, body.position.y, 7.0),
+			velocity: Vec3::new(body.velocity.x, body.velocity.y, 0.0),
+			size: body.size
+		};
+		let steer = Steer { velocity: lift(&boid).velocity, max_speed: 3.0, forces: &FORCES };
+		let deep = steer.flocking(&lift(&boid), others.iter().map(lift));
+		assert!(flat != Vec2::ZERO);
+		assert!((deep.x - flat.x).abs() < 1e-12 && (deep.y - flat.y).abs() < 1e-12 && deep.z == 0.0);
+	}
+}
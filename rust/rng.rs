@@ -0,0 +1,52 @@
+/// Small seedable generator (splitmix64) so that runs can be replayed,
+/// and so that the simulation doesn't depend on `Math.random` outside the browser
+#[derive(Clone)]
+pub struct Rng {
+	state: u64
+}
+
+impl Rng {
+	pub fn new(seed: u64) -> Rng {
+		Rng { state: seed }
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+		z ^ (z >> 31)
+	}
+
+	/// Uniform in [0, 1), like `Math.random`
+	pub fn random(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matches_the_reference_splitmix64() {
+		let mut rng = Rng::new(0);
+		let values: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+		assert_eq!(values, vec![0xe220a8397b1dcdaf, 0x6e789e6aa1b965f4, 0x06c45d188009454f]);
+	}
+
+	#[test]
+	fn same_seed_same_sequence() {
+		let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+		for _ in 0..100 {
+			assert_eq!(a.next_u64(), b.next_u64());
+		}
+		assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+	}
+
+	#[test]
+	fn random_stays_in_unit_interval() {
+		let mut rng = Rng::new(7);
+		assert!((0..10_000).map(|_| rng.random()).all(|value| (0.0..1.0).contains(&value)));
+	}
+}
@@ -0,0 +1,201 @@
+use crate::goal::turn_towards;
+use crate::universe::boid::{Boid, Environment};
+
+/// What a rule adds to a boid's angular and linear speeds, per frame
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Contribution {
+	/// positive turns left
+	pub turn: f64,
+	pub speed: f64
+}
+
+/// A steering rule of `Steering::Angular`, reacting to what a boid sees.
+/// Rules are shared between threads when the `parallel` feature is on
+pub trait Rule: Send + Sync {
+	/// identifies the rule in a `Pipeline`
+	fn name(&self) -> &str;
+
+	/// `neighbours` are the boids visible to `boid`
+	fn steer(&self, boid: &Boid, neighbours: &[Boid], environment: &Environment) -> Contribution;
+}
+
+/// Weighted rules applied one after the other. Weights scale the turn of each rule,
+/// speed contributions are taken as is.
+pub struct Pipeline {
+	rules: Vec<(Box<dyn Rule>, f64)>
+}
+
+impl Pipeline {
+	/// The original four rules of `Boid::update`, then goal seeking
+	pub fn new() -> Pipeline {
+		let mut pipeline = Pipeline::empty();
+		pipeline.push(Box::new(AvoidWalls), 2.0);
+		pipeline.push(Box::new(AvoidEntities), 0.2);
+		pipeline.push(Box::new(FollowGroup), 0.07);
+		pipeline.push(Box::new(GoToGroup), 0.02);
+		pipeline.push(Box::new(SeekGoals), 0.1);
+		pipeline
+	}
+
+	/// No rule at all, to build a pipeline from scratch
+	pub fn empty() -> Pipeline {
+		Pipeline { rules: vec![] }
+	}
+
+	pub fn push(&mut self, rule: Box<dyn Rule>, weight: f64) {
+		self.rules.push((rule, weight));
+	}
+
+	/// Returns whether a rule by that name was found
+	pub fn set_weight(&mut self, name: &str, weight: f64) -> bool {
+		match self.rules.iter_mut().find(|(rule, _)| rule.name() == name) {
+			Some(entry) => {
+				entry.1 = weight;
+				true
+			},
+			None => false
+		}
+	}
+
+	/// Returns whether a rule by that name was found
+	pub fn remove(&mut self, name: &str) -> bool {
+		let length = self.rules.len();
+		self.rules.retain(|(rule, _)| rule.name() != name);
+		self.rules.len() != length
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&dyn Rule, f64)> {
+		self.rules.iter().map(|(rule, weight)| (rule.as_ref(), *weight))
+	}
+}
+
+impl Default for Pipeline {
+	fn default() -> Pipeline {
+		Pipeline::new()
+	}
+}
+
+/// Turn away from walls ahead, harder and slower the closer they are
+pub struct AvoidWalls;
+
+impl Rule for AvoidWalls {
+	fn name(&self) -> &str { "avoid_walls" }
+
+	fn steer(&self, boid: &Boid, _: &[Boid], environment: &Environment) -> Contribution {
+		let (sees_wall, wall_angle, wall_distance) = boid.test_wall_visibility(environment.canvas);
+		if !sees_wall {
+			return Contribution::default()
+		}
+		Contribution {
+			turn: wall_angle.signum() / wall_distance,
+			speed: -0.03 * wall_distance / boid.vision().radius
+		}
+	}
+}
+
+/// Turn away from, and slow down near, boids that are too close
+pub struct AvoidEntities;
+
+impl Rule for AvoidEntities {
+	fn name(&self) -> &str { "avoid_entities" }
+
+	fn steer(&self, boid: &Boid, neighbours: &[Boid], _: &Environment) -> Contribution {
+		let (too_close, direction) = boid.find_closest_direction(neighbours);
+		if !too_close {
+			return Contribution::default()
+		}
+		Contribution {
+			turn: direction,
+			speed: -0.03
+		}
+	}
+}
+
+/// Turn towards the heading of visible boids, once there are enough of them
+pub struct FollowGroup;
+
+impl Rule for FollowGroup {
+	fn name(&self) -> &str { "follow_group" }
+
+	fn steer(&self, boid: &Boid, neighbours: &[Boid], environment: &Environment) -> Contribution {
+		let (sees_group, angle, count) = boid.find_group_direction(neighbours, &environment.settings.alignment);
+		if !sees_group || count <= 4 {
+			return Contribution::default()
+		}
+		Contribution {
+			turn: angle.signum(),
+			speed: 0.0
+		}
+	}
+}
+
+/// Turn towards the side where more boids are visible
+pub struct GoToGroup;
+
+impl Rule for GoToGroup {
+	fn name(&self) -> &str { "go_to_group" }
+
+	fn steer(&self, boid: &Boid, neighbours: &[Boid], _: &Environment) -> Contribution {
+		let (sees_group, direction) = boid.find_density_direction(neighbours);
+		if !sees_group {
+			return Contribution::default()
+		}
+		Contribution {
+			turn: direction,
+			speed: 0.0
+		}
+	}
+}
+
+/// Turn towards attractors and the next waypoint, away from repellers
+pub struct SeekGoals;
+
+impl Rule for SeekGoals {
+	fn name(&self) -> &str { "seek_goals" }
+
+	fn steer(&self, boid: &Boid, _: &[Boid], environment: &Environment) -> Contribution {
+		let goals = environment.goals;
+		let mut turn = 0.0;
+		for attractor in &goals.attractors {
+			let pull = attractor.pull(boid.x(), boid.y());
+			if pull != 0.0 {
+				turn += turn_towards(boid.heading(), attractor.x - boid.x(), attractor.y - boid.y()).signum() * pull;
+			}
+		}
+		if let Some((x, y)) = goals.waypoint(boid.waypoints_reached()) {
+			turn += turn_towards(boid.heading(), x - boid.x(), y - boid.y()).signum();
+		}
+		Contribution {
+			turn: turn * boid.goal_seeking(),
+			speed: 0.0
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn names(pipeline: &Pipeline) -> Vec<&str> {
+		pipeline.iter().map(|(rule, _)| rule.name()).collect()
+	}
+
+	#[test]
+	fn new_pipelines_hold_the_standard_rules() {
+		let standard = vec!["avoid_walls", "avoid_entities", "follow_group", "go_to_group", "seek_goals"];
+		assert_eq!(names(&Pipeline::new()), standard);
+		assert_eq!(names(&Pipeline::default()), standard);
+		assert!(names(&Pipeline::empty()).is_empty());
+	}
+
+	#[test]
+	fn rules_are_found_by_name() {
+		let mut pipeline = Pipeline::new();
+		assert!(pipeline.set_weight("follow_group", 0.5));
+		assert!(!pipeline.set_weight("teleport", 1.0));
+		assert_eq!(pipeline.iter().find(|(rule, _)| rule.name() == "follow_group").unwrap().1, 0.5);
+		assert!(pipeline.remove("avoid_walls"));
+		assert!(!pipeline.remove("avoid_walls"));
+		assert_eq!(names(&pipeline)[0], "avoid_entities");
+	}
+}
@@ -0,0 +1,459 @@
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+use crate::color::{ColorMode, Rgba, PALETTE};
+use crate::reynolds::{Body, Steer};
+use crate::rng::Rng;
+use crate::universe::boid::Forces;
+use crate::vec2::Vec2;
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Vec3 {
+	pub x: f64,
+	pub y: f64,
+	pub z: f64
+}
+
+impl Vec3 {
+	pub const ZERO: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+
+	pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
+		Vec3 { x, y, z }
+	}
+
+	pub fn dot(self, other: Vec3) -> f64 {
+		self.x * other.x + self.y * other.y + self.z * other.z
+	}
+
+	pub fn length(self) -> f64 {
+		self.dot(self).sqrt()
+	}
+
+	/// Same direction, given length. The zero vector stays as is
+	pub fn with_length(self, length: f64) -> Vec3 {
+		let norm = self.length();
+		if norm == 0.0 {
+			self
+		} else {
+			self * (length / norm)
+		}
+	}
+
+	pub fn with_max_length(self, length: f64) -> Vec3 {
+		if self.length() > length {
+			self.with_length(length)
+		} else {
+			self
+		}
+	}
+
+	/// Random direction, uniform on the sphere
+	fn random_unit(rng: &mut Rng) -> Vec3 {
+		let z = rng.random() * 2.0 - 1.0;
+		let azimuth = rng.random() * PI * 2.0;
+		let radius = (1.0 - z * z).sqrt();
+		Vec3::new(azimuth.cos() * radius, azimuth.sin() * radius, z)
+	}
+}
+
+impl Add for Vec3 {
+	type Output = Vec3;
+	fn add(self, other: Vec3) -> Vec3 {
+		Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+	}
+}
+
+impl AddAssign for Vec3 {
+	fn add_assign(&mut self, other: Vec3) {
+		*self = *self + other;
+	}
+}
+
+impl Sub for Vec3 {
+	type Output = Vec3;
+	fn sub(self, other: Vec3) -> Vec3 {
+		Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+	}
+}
+
+impl Mul<f64> for Vec3 {
+	type Output = Vec3;
+	fn mul(self, factor: f64) -> Vec3 {
+		Vec3::new(self.x * factor, self.y * factor, self.z * factor)
+	}
+}
+
+impl Div<f64> for Vec3 {
+	type Output = Vec3;
+	fn div(self, divisor: f64) -> Vec3 {
+		Vec3::new(self.x / divisor, self.y / divisor, self.z / divisor)
+	}
+}
+
+/// Boid of a `Space`, heading where its velocity points
+#[derive(Clone, Debug)]
+pub struct Boid3 {
+	pub id: u32,
+	pub position: Vec3,
+	pub velocity: Vec3,
+	/// vision range
+	pub radius: f64,
+	/// full opening angle of the vision cone, around the heading
+	pub radians: f64,
+	pub min_speed: f64,
+	pub max_speed: f64,
+	pub size: f64,
+	pub width: f64,
+	pub color: Rgba,
+	neighbours: usize
+}
+
+impl Boid3 {
+	fn new(id: u32, position: Vec3, rng: &mut Rng) -> Boid3 {
+		let speed = rng.random();
+		let size = rng.random();
+		let species = rng.random();
+		let purple = (species * 180.0).round() as u8;
+		let green = ((1.0 - species) * 180.0).round() as u8;
+		Boid3 {
+			id,
+			position,
+			velocity: Vec3::random_unit(rng) * 0.2,
+			radius: 100.0,
+			radians: 1.25 * PI,
+			min_speed: 0.2,
+			max_speed: 2.0 + speed,
+			size: 10.0 + size.min(2.0) * 15.0,
+			width: 5.0 + (1.0 - speed) * 10.0,
+			color: Rgba::rgb(purple, green, purple),
+			neighbours: 0
+		}
+	}
+
+	/// Unit vector of the direction the boid flies in
+	pub fn heading(&self) -> Vec3 {
+		self.velocity.with_length(1.0)
+	}
+
+	/// Within vision range, and inside the spherical cone around the heading
+	pub fn sees(&self, other: &Boid3) -> bool {
+		if self.id == other.id {
+			return false
+		}
+		let offset = other.position - self.position;
+		let distance = offset.length();
+		if distance > self.radius {
+			return false
+		}
+		distance == 0.0 || self.heading().dot(offset) >= (self.radians / 2.0).cos() * distance
+	}
+
+	pub fn color(&self, mode: &ColorMode) -> Rgba {
+		match mode {
+			ColorMode::Speed => {
+				let ratio = (self.velocity.length() - self.min_speed) / (self.max_speed - self.min_speed);
+				Rgba::rgb(40, 80, 220).lerp(Rgba::rgb(230, 40, 40), ratio)
+			},
+			// hue of the heading projected on the horizontal plane
+			ColorMode::Heading => Rgba::from_hue((- self.velocity.x).atan2(- self.velocity.z).rem_euclid(PI * 2.0)),
+			ColorMode::Neighbours => Rgba::rgb(30, 30, 60).lerp(Rgba::rgb(255, 230, 80), self.neighbours as f64 / 20.0),
+			ColorMode::Palette => PALETTE[self.id as usize % PALETTE.len()],
+			ColorMode::Species | ColorMode::Cluster => self.color
+		}
+	}
+}
+
+/// Boids bucketed by cell of a regular 3D grid, cells as large as the largest vision range
+struct Grid {
+	size: f64,
+	columns: usize,
+	rows: usize,
+	layers: usize,
+	cells: Vec<Vec<usize>>
+}
+
+impl Grid {
+	fn new(bounds: Vec3, boids: &[Boid3]) -> Grid {
+		let size = boids.iter().map(|boid| boid.radius).fold(1.0, f64::max);
+		let count = |length: f64| ((length / size).ceil() as usize).max(1);
+		let (columns, rows, layers) = (count(bounds.x), count(bounds.y), count(bounds.z));
+		let mut grid = Grid {
+			size,
+			columns,
+			rows,
+			layers,
+			cells: vec![Vec::new(); columns * rows * layers]
+		};
+		for (i, boid) in boids.iter().enumerate() {
+			let (column, row, layer) = grid.cell(boid.position);
+			grid.cells[(column * rows + row) * layers + layer].push(i);
+		}
+		grid
+	}
+
+	fn cell(&self, position: Vec3) -> (usize, usize, usize) {
+		let index = |value: f64, count: usize| ((value / self.size).floor().max(0.0) as usize).min(count - 1);
+		(index(position.x, self.columns), index(position.y, self.rows), index(position.z, self.layers))
+	}
+
+	/// Indices of boids in the 27 cells around `position`
+	fn candidates(&self, position: Vec3) -> impl Iterator<Item = usize> + '_ {
+		let (column, row, layer) = self.cell(position);
+		let around = |index: usize, count: usize| index.saturating_sub(1)..(index + 2).min(count);
+		around(column, self.columns)
+			.flat_map(move |c| around(row, self.rows).map(move |r| (c, r)))
+			.flat_map(move |(c, r)| around(layer, self.layers).map(move |l| (c * self.rows + r) * self.layers + l))
+			.flat_map(move |cell| self.cells[cell].iter().copied())
+	}
+}
+
+/// Flock in a box, x and y along the canvas, z going away from the viewer
+pub struct Space {
+	pub bounds: Vec3,
+	pub padding: f64,
+	pub boids: Vec<Boid3>,
+	next_id: u32
+}
+
+impl Space {
+	/// The box is as deep as the canvas' smallest side
+	pub fn new(width: f64, height: f64, count: usize, rng: &mut Rng) -> Space {
+		let mut space = Space {
+			bounds: Vec3::new(width, height, width.min(height)),
+			padding: 12.5,
+			boids: vec![],
+			next_id: 0
+		};
+		for _ in 0..count {
+			let position = Vec3::new(
+				rng.random() * space.bounds.x,
+				rng.random() * space.bounds.y,
+				rng.random() * space.bounds.z
+			);
+			space.add(position, rng);
+		}
+		space
+	}
+
+	pub fn add(&mut self, position: Vec3, rng: &mut Rng) {
+		self.next_id += 1;
+		self.boids.push(Boid3::new(self.next_id, position, rng));
+	}
+
+	pub fn resize(&mut self, width: f64, height: f64) {
+		self.bounds = Vec3::new(width, height, width.min(height));
+	}
+
+	/// Same forces as `Steering::Reynolds`, walls being the faces of the box
+	pub fn tick(&mut self, frames: f64, forces: &Forces) {
+		let grid = Grid::new(self.bounds, &self.boids);
+		let clone = self.boids.clone();
+		let (bounds, padding) = (self.bounds, self.padding);
+		for boid in self.boids.iter_mut() {
+			let visible: Vec<&Boid3> = grid
+				.candidates(boid.position)
+				.map(|i| &clone[i])
+				.filter(|other| boid.sees(other))
+				.collect();
+			boid.neighbours = visible.len();
+
+			let velocity = boid.velocity;
+			let steering = Steer { velocity, max_speed: boid.max_speed, forces };
+			let body = |boid: &Boid3| Body { position: boid.position, velocity: boid.velocity, size: boid.size };
+			let mut acceleration = steering.flocking(&body(boid), visible.iter().map(|other| body(other)));
+
+			// walls: back towards the inside when closer than half the vision range
+			let margin = padding + boid.radius / 2.0;
+			let inward = |value: f64, length: f64| if value < margin { 1.0 } else if value > length - margin { -1.0 } else { 0.0 };
+			let inward = Vec3::new(
+				inward(boid.position.x, bounds.x),
+				inward(boid.position.y, bounds.y),
+				inward(boid.position.z, bounds.z)
+			);
+			if inward != Vec3::ZERO {
+				acceleration += steering.towards(inward, forces.avoid_walls);
+			}
+
+			let velocity = velocity + acceleration * frames;
+			let speed = velocity.length().min(boid.max_speed).max(boid.min_speed);
+			boid.velocity = if velocity == Vec3::ZERO { boid.velocity } else { velocity.with_length(speed) };
+
+			let position = boid.position + boid.velocity * frames;
+			// sides under twice the padding keep boids in their middle
+			let clamp = |value: f64, length: f64| {
+				let padding = padding.min(length / 2.0);
+				value.clamp(padding, length - padding)
+			};
+			boid.position = Vec3::new(
+				clamp(position.x, bounds.x),
+				clamp(position.y, bounds.y),
+				clamp(position.z, bounds.z)
+			);
+		}
+	}
+
+	/// Distance from the eye to the front face of the box
+	fn focal(&self) -> f64 {
+		self.bounds.z * 2.0
+	}
+
+	/// Perspective projection on the canvas, with the scale at that depth
+	pub fn project(&self, point: Vec3) -> (Vec2, f64) {
+		let scale = self.focal() / (self.focal() + point.z);
+		let center = Vec2::new(self.bounds.x / 2.0, self.bounds.y / 2.0);
+		(center + (Vec2::new(point.x, point.y) - center) * scale, scale)
+	}
+
+	/// Point at depth `z` that `project` puts at `point` of the canvas
+	pub fn unproject(&self, point: Vec2, z: f64) -> Vec3 {
+		let scale = self.focal() / (self.focal() + z);
+		let center = Vec2::new(self.bounds.x / 2.0, self.bounds.y / 2.0);
+		let point = center + (point - center) / scale;
+		Vec3::new(point.x, point.y, z)
+	}
+
+	/// Projected triangle and colour of every boid, farthest first, smaller and paler the deeper they are
+	pub fn triangles(&self, mode: &ColorMode) -> Vec<([Vec2; 3], Rgba)> {
+		let mut order: Vec<&Boid3> = self.boids.iter().collect();
+		order.sort_by(|a, b| b.position.z.total_cmp(&a.position.z));
+		order
+			.into_iter()
+			.map(|boid| {
+				let heading = boid.heading();
+				let (tip, _) = self.project(boid.position + heading * (boid.size * 0.6));
+				let (tail, scale) = self.project(boid.position - heading * (boid.size * 0.4));
+				let along = tip - tail;
+				let half_width = boid.width * scale / 2.0;
+				let normal = Vec2::new(- along.y, along.x) * (half_width / along.length().max(1.0));
+
+				let fog = (boid.position.z / self.bounds.z).clamp(0.0, 1.0) * 0.7;
+				let color = boid.color(mode).lerp(Rgba::rgb(255, 255, 255), fog);
+				([tip, tail + normal, tail - normal], color)
+			})
+			.collect()
+	}
+
+	pub fn draw(&self, context: &web_sys::CanvasRenderingContext2d, mode: &ColorMode) {
+		self.draw_box(context);
+		for ([tip, first, second], color) in self.triangles(mode) {
+			context.set_fill_style_str(&color.to_css());
+			context.begin_path();
+			context.move_to(tip.x, tip.y);
+			context.line_to(first.x, first.y);
+			context.line_to(second.x, second.y);
+			context.fill();
+		}
+	}
+
+	pub fn draw_box(&self, context: &web_sys::CanvasRenderingContext2d) {
+		let corner = |i: usize| self.project(Vec3::new(
+			if i & 1 == 0 { 0.0 } else { self.bounds.x },
+			if i & 2 == 0 { 0.0 } else { self.bounds.y },
+			if i & 4 == 0 { 0.0 } else { self.bounds.z }
+		)).0;
+		context.set_stroke_style_str("lightgray");
+		context.begin_path();
+		for a in 0..8 {
+			// corners that differ by a single coordinate share an edge
+			for bit in &[1, 2, 4] {
+				if a & bit == 0 {
+					let (from, to) = (corner(a), corner(a | bit));
+					context.move_to(from.x, from.y);
+					context.line_to(to.x, to.y);
+				}
+			}
+		}
+		context.stroke();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::universe::boid::Settings;
+
+	fn forces() -> Forces {
+		Settings::default().forces
+	}
+
+	fn close(a: Vec2, b: Vec2) -> bool {
+		(a - b).length() < 1e-9
+	}
+
+	#[test]
+	fn projection_shrinks_towards_the_center_with_depth() {
+		let space = Space::new(400.0, 300.0, 0, &mut Rng::new(1));
+		let center = Vec2::new(200.0, 150.0);
+		// the front face is drawn as is, the back one at two thirds of the size
+		assert_eq!(space.project(Vec3::new(0.0, 0.0, 0.0)), (Vec2::new(0.0, 0.0), 1.0));
+		let (corner, scale) = space.project(Vec3::new(400.0, 300.0, 300.0));
+		assert!((scale - 2.0 / 3.0).abs() < 1e-12);
+		assert!(close(corner, center + Vec2::new(200.0, 150.0) * (2.0 / 3.0)));
+		assert_eq!(space.project(Vec3::new(200.0, 150.0, 123.0)).0, center);
+	}
+
+	#[test]
+	fn unproject_inverts_project() {
+		let space = Space::new(400.0, 300.0, 0, &mut Rng::new(1));
+		for (x, y, z) in [(10.0, 20.0, 0.0), (390.0, 5.0, 150.0), (123.0, 280.0, 300.0)] {
+			let point = Vec3::new(x, y, z);
+			let back = space.unproject(space.project(point).0, z);
+			assert!((back - point).length() < 1e-9, "{:?} came back as {:?}", point, back);
+		}
+	}
+
+	#[test]
+	fn boids_stay_in_the_box_at_bounded_speeds() {
+		let mut rng = Rng::new(2);
+		let mut space = Space::new(400.0, 300.0, 60, &mut rng);
+		for _ in 0..200 {
+			space.tick(1.0, &forces());
+		}
+		let (bounds, padding) = (space.bounds, space.padding);
+		for boid in &space.boids {
+			let inside = |value: f64, length: f64| (padding..=length - padding).contains(&value);
+			assert!(inside(boid.position.x, bounds.x) && inside(boid.position.y, bounds.y) && inside(boid.position.z, bounds.z));
+			let speed = boid.velocity.length();
+			assert!(speed >= boid.min_speed - 1e-9 && speed <= boid.max_speed + 1e-9, "speed {}", speed);
+		}
+	}
+
+	#[test]
+	fn boids_stay_in_a_box_thinner_than_its_padding() {
+		let mut rng = Rng::new(5);
+		let mut space = Space::new(400.0, 20.0, 30, &mut rng);
+		for _ in 0..50 {
+			space.tick(1.0, &forces());
+		}
+		let bounds = space.bounds;
+		for boid in &space.boids {
+			let inside = |value: f64, length: f64| (0.0..=length).contains(&value);
+			assert!(inside(boid.position.x, bounds.x) && inside(boid.position.y, bounds.y) && inside(boid.position.z, bounds.z));
+		}
+	}
+
+	#[test]
+	fn a_lone_boid_flies_straight() {
+		let mut rng = Rng::new(3);
+		let mut space = Space::new(400.0, 300.0, 0, &mut rng);
+		space.add(Vec3::new(200.0, 150.0, 150.0), &mut rng);
+		space.boids[0].velocity = Vec3::new(0.0, 0.0, 1.0);
+		space.tick(2.0, &forces());
+		assert_eq!(space.boids[0].position, Vec3::new(200.0, 150.0, 152.0));
+		assert_eq!(space.boids[0].velocity, Vec3::new(0.0, 0.0, 1.0));
+	}
+
+	#[test]
+	fn neighbours_steer_together() {
+		let mut rng = Rng::new(4);
+		let mut space = Space::new(400.0, 300.0, 0, &mut rng);
+		space.add(Vec3::new(180.0, 150.0, 150.0), &mut rng);
+		space.add(Vec3::new(220.0, 150.0, 150.0), &mut rng);
+		space.boids[0].velocity = Vec3::new(1.0, 0.0, 0.0);
+		space.boids[1].velocity = Vec3::new(0.0, 0.0, 1.0);
+		space.tick(1.0, &forces());
+		// each turned towards the heading of the other
+		assert!(space.boids[0].velocity.z > 0.0);
+		assert!(space.boids[1].velocity.x > 0.0);
+		assert_eq!(space.boids[0].neighbours, 1);
+	}
+}
@@ -0,0 +1,146 @@
+//! Runs canonical scenarios and compares the trajectories of their boids with the CSV fixtures
+//! in rust/tests/fixtures, within `TOLERANCE`. A refactor of `Boid::update` that isn't meant to
+//! change behaviour must keep them passing. After an intended change, the fixtures are rewritten with
+//!
+//! ```
+//! UPDATE_FIXTURES=1 cargo test --test golden
+//! ```
+
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::path::PathBuf;
+use boids::flock::Flock;
+use boids::universe::Universe;
+
+const TOLERANCE: f64 = 1e-6;
+
+/// One line per boid every `every` ticks, including the starting positions
+struct Trajectory {
+	rows: Vec<Row>
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Row {
+	tick: u32,
+	id: u32,
+	x: f64,
+	y: f64,
+	angle: f64
+}
+
+fn run(universe: &mut Universe, ticks: u32, every: u32) -> Trajectory {
+	let mut rows = vec![];
+	for tick in 0..=ticks {
+		if tick % every == 0 {
+			let flock = &universe.flock;
+			rows.extend((0..flock.len()).map(|i| Row {
+				tick,
+				id: flock.id[i],
+				x: flock.x[i],
+				y: flock.y[i],
+				angle: flock.angle[i]
+			}));
+		}
+		if tick < ticks {
+			universe.tick(1.0, false);
+		}
+	}
+	Trajectory { rows }
+}
+
+/// Universe without any boid but those placed with `place`
+fn empty(seed: u64) -> Universe {
+	let mut universe = Universe::new(None, 1920.0, 1080.0, seed);
+	universe.flock = Flock::new();
+	universe
+}
+
+/// Boid at (x, y) with the given heading, at full speed
+fn place(universe: &mut Universe, x: f64, y: f64, angle: f64) {
+	universe.add_one_boid_xy(x, y);
+	let flock = &mut universe.flock;
+	let index = flock.len() - 1;
+	flock.angle[index] = angle;
+	flock.drawing_angle[index] = angle;
+	flock.linear_speed[index] = flock.traits[index].linear_speed.max;
+}
+
+fn fixture(name: &str) -> PathBuf {
+	PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("rust/tests/fixtures").join(format!("{}.csv", name))
+}
+
+fn to_csv(trajectory: &Trajectory) -> String {
+	let mut csv = String::from("tick,id,x,y,angle\n");
+	for row in &trajectory.rows {
+		writeln!(csv, "{},{},{:?},{:?},{:?}", row.tick, row.id, row.x, row.y, row.angle).unwrap();
+	}
+	csv
+}
+
+fn from_csv(csv: &str) -> Trajectory {
+	let rows = csv
+		.lines()
+		.skip(1)
+		.map(|line| {
+			let fields: Vec<&str> = line.split(',').collect();
+			Row {
+				tick: fields[0].parse().unwrap(),
+				id: fields[1].parse().unwrap(),
+				x: fields[2].parse().unwrap(),
+				y: fields[3].parse().unwrap(),
+				angle: fields[4].parse().unwrap()
+			}
+		})
+		.collect();
+	Trajectory { rows }
+}
+
+/// Smallest difference between two headings, in radians
+fn angle_difference(a: f64, b: f64) -> f64 {
+	let difference = (a - b).rem_euclid(PI * 2.0);
+	difference.min(PI * 2.0 - difference)
+}
+
+fn check(name: &str, actual: Trajectory) {
+	let path = fixture(name);
+	if std::env::var_os("UPDATE_FIXTURES").is_some() {
+		std::fs::write(&path, to_csv(&actual)).unwrap();
+		return
+	}
+	let expected = std::fs::read_to_string(&path)
+		.unwrap_or_else(|error| panic!("cannot read {}: {}, run with UPDATE_FIXTURES=1 to create it", path.display(), error));
+	let expected = from_csv(&expected);
+	assert_eq!(expected.rows.len(), actual.rows.len(), "{}: number of rows", name);
+	for (expected, actual) in expected.rows.iter().zip(&actual.rows) {
+		let same = expected.tick == actual.tick
+			&& expected.id == actual.id
+			&& (expected.x - actual.x).abs() <= TOLERANCE
+			&& (expected.y - actual.y).abs() <= TOLERANCE
+			&& angle_difference(expected.angle, actual.angle) <= TOLERANCE;
+		assert!(same, "{}: trajectories diverge\nexpected {:?}\n     got {:?}", name, expected, actual);
+	}
+}
+
+#[test]
+fn single_boid_into_a_corner() {
+	let mut universe = empty(1);
+	// heading up and left, straight for the top left corner
+	place(&mut universe, 300.0, 300.0, PI / 4.0);
+	check("corner", run(&mut universe, 300, 5));
+}
+
+#[test]
+fn two_boids_on_a_collision_course() {
+	let mut universe = empty(2);
+	// face to face along the middle of the canvas, one heading right and one heading left
+	place(&mut universe, 760.0, 540.0, PI * 1.5);
+	place(&mut universe, 1160.0, 540.0, PI / 2.0);
+	check("collision", run(&mut universe, 200, 5));
+}
+
+#[test]
+fn flock_of_200() {
+	let mut universe = Universe::new(None, 1920.0, 1080.0, 3);
+	assert_eq!(universe.flock.len(), 200);
+	check("flock", run(&mut universe, 100, 20));
+}
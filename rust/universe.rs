@@ -1,117 +1,615 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use crate::arena::Arena;
+use crate::cluster::Clustering;
+use crate::color::{ColorMode, Rgba, PALETTE};
+use crate::flock::Flock;
+use crate::flow::Flow;
+use crate::goal::Goals;
+use crate::mask::Mask;
+use crate::metrics::Metrics;
+use crate::profile::{self, Counters, Profiler};
+use crate::render::RenderBuffer;
+use crate::rng::Rng;
+use crate::rules::Pipeline;
+use crate::space::Space;
+use crate::vec2::Vec2;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-// #[path = "Boid_uses_Angle.rs"]
 #[path = "Boid.rs"]
-mod boid;
+pub mod boid;
 
 pub struct Universe {
 	pub canvas: boid::Canvas,
-	pub boids: Vec<boid::Boid>,
-	context: web_sys::CanvasRenderingContext2d
+	pub flock: Flock,
+	pub color_mode: ColorMode,
+	pub settings: boid::Settings,
+	pub rules: Pipeline,
+	pub goals: Goals,
+	pub flow: Flow,
+	/// frames elapsed since creation
+	time: f64,
+	/// measured at the start of the last tick, all zero while `space` is set
+	pub metrics: Metrics,
+	/// flocks detected at the start of the last tick, `None` when detection is off
+	/// and empty while `space` is set
+	pub clustering: Option<Clustering>,
+	/// 3D flock simulated and drawn instead of `flock` when set
+	pub space: Option<Space>,
+	/// filled by `fill_render_buffer`, for renderers outside of Rust
+	pub render_buffer: RenderBuffer,
+	/// whether `render` draws boids of close colours together, see `RenderBuffer::draw`
+	pub batched: bool,
+	/// where ticks and frames spend their time, `None` unless profiling
+	pub profiler: Option<Profiler>,
+	rng: Rng,
+	context: Option<web_sys::CanvasRenderingContext2d>
 }
 
 impl Universe {
 
-	pub fn new(context: web_sys::CanvasRenderingContext2d, width: f64, height: f64) -> Universe {
+	/// Without a `context`, the universe can still tick but `render` does nothing
+	pub fn new(context: Option<web_sys::CanvasRenderingContext2d>, width: f64, height: f64, seed: u64) -> Universe {
 		let canvas = boid::Canvas {
 			width,
 			height,
-			padding: 12.5
+			padding: 12.5,
+			arena: None,
+			mask: None
 		};
 
-		let mut boids: Vec<boid::Boid> = vec![];
-
-		for _ in 0..200 {
-			let mut boid = boid::Boid::new();
-			boid.set_all_initial(&canvas);
-			boids.push(boid);
-		}
-
-		Universe {
+		let mut universe = Universe {
 			canvas,
-			boids,
+			flock: Flock::new(),
+			color_mode: ColorMode::Species,
+			settings: boid::Settings::default(),
+			rules: Pipeline::new(),
+			goals: Goals::new(),
+			flow: Flow::default(),
+			time: 0.0,
+			metrics: Metrics::default(),
+			clustering: None,
+			space: None,
+			render_buffer: RenderBuffer::default(),
+			batched: true,
+			profiler: None,
+			rng: Rng::new(seed),
 			context
+		};
+		universe.add_boids(200);
+		universe
+	}
+
+	/// Boids at random open points of the canvas
+	pub fn add_boids(&mut self, count: usize) {
+		for _ in 0..count {
+			let index = self.flock.spawn(None, &self.canvas, &mut self.rng);
+			self.keep_in_bounds(index);
 		}
 	}
 
 	pub fn tick(&mut self, frames: f64, debug: bool) {
-		let clone = self.boids.clone();
-		let references: Vec<&boid::Boid> = clone.iter().collect();
-		let (boids_map, boids_cells) = grid_split(&self.canvas, references);
-		let empty = Vec::new();
-		for boid in self.boids.iter_mut() {
-			let boids = boids_map.get(&boid.id).map_or(
-				&empty, 
-				|(col, row)| &boids_cells[*col][*row]
-			);
-			
-			boid.update(&self.canvas, &boids, frames);
-		}
-
-		if debug {
-			let boid = &mut self.boids[0];
-			let empty = Vec::new();
-			boid.draw_connections(&self.context, &(
-				boids_map.get(&boid.id).map_or(
-					&empty, 
-					|(col, row)| &boids_cells[*col][*row]
-				).to_owned()
-			));
+		let profiling = self.profiler.is_some();
+		// timestamps are only read while profiling
+		let now = || if profiling { profile::now() } else { 0.0 };
+		let start = now();
+		if let Some(space) = &mut self.space {
+			space.tick(frames, &self.settings.forces);
+			// the 3D flock isn't measured, and what was measured of the 2D one no longer shows
+			self.metrics = Metrics::default();
+			if let Some(clustering) = &mut self.clustering {
+				*clustering = Clustering::new(clustering.linkage);
+			}
+			if let Some(profiler) = &mut self.profiler {
+				profiler.record_since("tick_ms", start);
+			}
+			return
+		}
+		let flock = &self.flock;
+		let boids: Vec<boid::Boid> = flock.iter().collect();
+		let grid = match self.settings.neighbourhood {
+			boid::Neighbourhood::Topological(_) => Some(Grid::new(&self.canvas, &boids)),
+			boid::Neighbourhood::Metric => None
+		};
+		let split_start = now();
+		let (boids_map, boids_cells) = grid_split(&self.canvas, &boids);
+		let split_end = now();
+		let counters = profiling.then(Counters::default);
+		let candidates = |boid: &boid::Boid| {
+			let (col, row) = boids_map[boid.index()];
+			boids_cells.get(col, row)
+		};
+		let environment = boid::Environment {
+			canvas: &self.canvas,
+			settings: &self.settings,
+			rules: &self.rules,
+			goals: &self.goals,
+			flow: &self.flow,
+			time: self.time,
+			counters: counters.as_ref()
+		};
+		// boids each boid may see: its k nearest in view with a topological neighbourhood, its cell otherwise
+		let neighbourhood = self.settings.neighbourhood;
+		let neighbours_of = |boid: &boid::Boid| -> Cow<[boid::Boid]> {
+			match (&grid, neighbourhood) {
+				(Some(grid), boid::Neighbourhood::Topological(k)) => Cow::Owned(grid.nearest(boid, k)),
+				_ => Cow::Borrowed(candidates(boid))
+			}
+		};
+		let update = |boid: &boid::Boid| boid.update(&environment, &neighbours_of(boid), frames);
+		// updates only read the flock, so they can run in any order and still be the same
+		#[cfg(feature = "parallel")]
+		let motions: Vec<boid::Motion> = boids.par_iter().map(update).collect();
+		#[cfg(not(feature = "parallel"))]
+		let motions: Vec<boid::Motion> = boids.iter().map(update).collect();
+
+		let metrics_start = now();
+		let neighbours: Vec<usize> = motions.iter().map(|motion| motion.neighbours).collect();
+		self.metrics = Metrics::measure(flock, candidates, &neighbours);
+		if let Some(clustering) = &mut self.clustering {
+			clustering.update(flock, candidates);
+		}
+		let metrics_end = now();
+
+		// each rule over the whole flock in a pass of its own: per boid, rules take less than a timer tick.
+		// The pass isn't part of the tick, it is left out of `tick_ms`
+		let mut rule_times = vec![];
+		let rules_start = now();
+		if profiling && self.settings.steering == boid::Steering::Angular {
+			let visible: Vec<Vec<boid::Boid>> = boids.iter().map(|boid| boid.visible(&environment, &neighbours_of(boid))).collect();
+			for (rule, _) in self.rules.iter() {
+				let rule_start = now();
+				for (boid, visible) in boids.iter().zip(&visible) {
+					std::hint::black_box(rule.steer(boid, visible, &environment));
+				}
+				rule_times.push((rule.name(), now() - rule_start));
+			}
+		}
+		let rules_time = now() - rules_start;
+
+		if let (Some(profiler), Some(counters)) = (&mut self.profiler, &counters) {
+			profiler.record("grid_split_ms", split_end - split_start);
+			profiler.record("update_ms", metrics_start - split_end);
+			profiler.record("metrics_ms", metrics_end - metrics_start);
+			for (name, time) in rule_times {
+				profiler.record(&format!("rule_ms:{}", name), time);
+			}
+			profiler.record("candidates", Counters::get(&counters.candidates) as f64);
+			profiler.record("visible", Counters::get(&counters.visible) as f64);
+		}
+
+		if let (true, Some(context), Some(boid)) = (debug, &self.context, boids.first()) {
+			boid.draw_connections(context, candidates(boid));
+		}
+
+		for (index, motion) in motions.iter().enumerate() {
+			self.flock.apply(index, motion);
+		}
+		self.time += frames;
+		if let Some(profiler) = &mut self.profiler {
+			profiler.record("tick_ms", now() - start - rules_time);
 		}
 	}
 
 	pub fn render(&mut self, draw_field_of_view: bool, debug: bool) {
-		self.context.clear_rect(0.0, 0.0, self.canvas.width, self.canvas.height);
-		for (i, boid) in self.boids.iter().enumerate() {
-			boid.draw(&self.context, draw_field_of_view || (i == 0 && debug));
+		if self.context.is_none() {
+			return
+		}
+		let start = self.profiler.as_ref().map(|_| profile::now());
+		self.draw(draw_field_of_view, debug);
+		if let (Some(profiler), Some(start)) = (&mut self.profiler, start) {
+			profiler.record_since("render_ms", start);
+		}
+	}
+
+	fn draw(&mut self, draw_field_of_view: bool, debug: bool) {
+		if self.batched {
+			self.fill_render_buffer();
+		}
+		let context = self.context.as_ref().unwrap();
+		context.clear_rect(0.0, 0.0, self.canvas.width, self.canvas.height);
+		if let Some(space) = &self.space {
+			if self.batched {
+				space.draw_box(context);
+				self.render_buffer.draw(context, false);
+			} else {
+				space.draw(context, &self.color_mode);
+			}
+			return
+		}
+		if let Some(arena) = &self.canvas.arena {
+			arena.draw(context);
+		}
+		self.goals.draw(context);
+		if self.batched {
+			for (i, boid) in self.flock.iter().enumerate() {
+				if draw_field_of_view || (i == 0 && debug) {
+					boid.draw_field_of_view(context, &self.color_of(&boid).to_css());
+				}
+			}
+			// boids are flat, the order they are drawn in only matters where they overlap
+			self.render_buffer.draw(context, true);
+		} else {
+			for (i, boid) in self.flock.iter().enumerate() {
+				boid.draw(context, self.color_of(&boid), draw_field_of_view || (i == 0 && debug));
+			}
+		}
+	}
+
+	/// Same triangles and colours as `render`, in the same order, without the field of view,
+	/// goals and arena
+	pub fn fill_render_buffer(&mut self) {
+		let mut buffer = std::mem::take(&mut self.render_buffer);
+		buffer.clear();
+		match &self.space {
+			Some(space) => for (triangle, color) in space.triangles(&self.color_mode) {
+				buffer.push(triangle, color);
+			},
+			None => for boid in self.flock.iter() {
+				buffer.push(boid.get_drawing_data(), self.color_of(&boid));
+			}
+		}
+		self.render_buffer = buffer;
+	}
+
+	fn color_of(&self, boid: &boid::Boid) -> Rgba {
+		match (&self.color_mode, &self.clustering) {
+			(ColorMode::Cluster, Some(clustering)) => clustering
+				.label(boid.id())
+				.map_or(boid.traits().color, |label| PALETTE[label as usize % PALETTE.len()]),
+			(mode, _) => boid.color(mode)
 		}
 	}
 
+	/// Switches between the 2D flock and a 3D one with as many boids. The 2D flock is kept
+	/// as it was, the 3D one starts over each time
+	pub fn set_space(&mut self, enabled: bool) {
+		self.space = if enabled {
+			Some(Space::new(self.canvas.width, self.canvas.height, self.flock.len(), &mut self.rng))
+		} else {
+			None
+		};
+	}
+
+	pub fn boids_count(&self) -> usize {
+		match &self.space {
+			Some(space) => space.boids.len(),
+			None => self.flock.len()
+		}
+	}
+
+	/// Removes the boid closest to (x, y) if it is within `tolerance`. In 3D, that is where the boid
+	/// is drawn, whatever its depth. Returns whether there was one
+	pub fn remove_boid_at(&mut self, x: f64, y: f64, tolerance: f64) -> bool {
+		let point = Vec2::new(x, y);
+		if let Some(space) = &mut self.space {
+			let distances = space.boids.iter().map(|boid| point.distance(space.project(boid.position).0));
+			return closest(distances, tolerance).map(|index| space.boids.swap_remove(index)).is_some()
+		}
+		let distances = self.flock.iter().map(|boid| point.distance(boid.point()));
+		closest(distances, tolerance).map(|index| self.flock.remove(index)).is_some()
+	}
+
+	/// In 3D, the new boid starts halfway into the box, where it shows under (x, y)
 	pub fn add_one_boid_xy(&mut self, x: f64, y: f64) {
-		let mut boid = boid::Boid::new();
-		boid.set_initial_coordinates(x, y);
-		boid.set_all_initial(&self.canvas);
-		boid.body.color = String::from("red");
-		self.boids.push(boid);
+		if let Some(space) = &mut self.space {
+			let position = space.unproject(Vec2::new(x, y), space.bounds.z / 2.0);
+			space.add(position, &mut self.rng);
+			return
+		}
+		let index = self.flock.spawn(Some(boid::Point { x, y }), &self.canvas, &mut self.rng);
+		self.flock.traits[index].color = Rgba::RED;
+		self.keep_in_bounds(index);
+	}
+
+	/// Boids outside of the new arena are moved to a random point inside
+	pub fn set_arena(&mut self, arena: Option<Arena>) {
+		self.canvas.arena = arena;
+		self.relocate_boids();
+	}
+
+	/// Boids on solid pixels of the new mask are moved to a random open point
+	pub fn set_mask(&mut self, mask: Option<Mask>) {
+		self.canvas.mask = mask;
+		self.relocate_boids();
+	}
+
+	fn relocate_boids(&mut self) {
+		for index in 0..self.flock.len() {
+			self.keep_in_bounds(index);
+		}
 	}
+
+	fn keep_in_bounds(&mut self, index: usize) {
+		for _ in 0..1000 {
+			if self.canvas.is_open(self.flock.x[index], self.flock.y[index]) {
+				return
+			}
+			self.flock.x[index] = self.rng.random() * self.canvas.width;
+			self.flock.y[index] = self.rng.random() * self.canvas.height;
+		}
+	}
+}
+
+/// Boids in or around each cell of `grid_split`, in a single array, cell after cell
+pub struct Cells<'a> {
+	rows: usize,
+	/// cell `i` spans from `starts[i]` to `starts[i + 1]`
+	starts: Vec<usize>,
+	boids: Vec<boid::Boid<'a>>
+}
+
+impl<'a> Cells<'a> {
+	pub fn get(&self, column: usize, row: usize) -> &[boid::Boid<'a>] {
+		let cell = column * self.rows + row;
+		&self.boids[self.starts[cell]..self.starts[cell + 1]]
+	}
+}
+
+/// Index of the smallest distance, if it is within `tolerance`
+fn closest(distances: impl Iterator<Item = f64>, tolerance: f64) -> Option<usize> {
+	distances
+		.enumerate()
+		.filter(|(_, distance)| *distance <= tolerance)
+		.min_by(|a, b| a.1.total_cmp(&b.1))
+		.map(|(index, _)| index)
 }
 
-fn grid_split<'a>(canvas: &boid::Canvas, boids: Vec<&'a boid::Boid>) -> (HashMap<u32, (usize, usize)>, Vec<Vec<Vec<&'a boid::Boid>>>) {
+/// Cell of each boid, by index in the flock, and the boids in or around each cell
+pub fn grid_split<'a>(canvas: &boid::Canvas, boids: &[boid::Boid<'a>]) -> (Vec<(usize, usize)>, Cells<'a>) {
 	let max_vision_range = boids
-		.clone()
 		.iter()
-		.map(|boid| boid.vision.radius)
-		.fold(0./0., f64::max)
+		.map(|boid| boid.vision().radius)
+		.fold(f64::NEG_INFINITY, f64::max)
 		.max(1.0);
 	let nb_columns = (canvas.width / max_vision_range).ceil() as i32;
 	let nb_rows = (canvas.height / max_vision_range).ceil() as i32;
-	
-	let mut cells = vec![vec![Vec::new(); nb_rows as usize]; nb_columns as usize];
-	let mut map = HashMap::new();
 
+	let mut map = vec![(0, 0); boids.len()];
 	for boid in boids {
-		let max_x = boid.point.x.min(canvas.width);
-		let column = (max_x / max_vision_range).floor() as i32;
+		let column = (boid.x() / max_vision_range).floor() as i32;
+		let row = (boid.y() / max_vision_range).floor() as i32;
+		map[boid.index()] = (column.clamp(0, nb_columns - 1) as usize, row.clamp(0, nb_rows - 1) as usize);
+	}
 
-		let max_y = boid.point.y.min(canvas.height);
-		let row = (max_y / max_vision_range).floor() as i32;
+	// each boid goes in its own cell and in the 8 around it
+	let around = |(column, row): (usize, usize)| {
+		let (column, row) = (column as i32, row as i32);
+		[-1, 0, 1]
+			.iter()
+			.flat_map(move |delta_column| [-1, 0, 1].iter().map(move |delta_row| (column + delta_column, row + delta_row)))
+			.filter(|(column, row)| *column >= 0 && *column < nb_columns && *row >= 0 && *row < nb_rows)
+			.map(|(column, row)| column as usize * nb_rows as usize + row as usize)
+	};
 
-		for delta_column in &[-1, 0, 1] {
-			for delta_row in &[-1, 0, 1] {
-				let target_column = column + delta_column;
-				if target_column < 0 || target_column >= nb_columns {
-					continue;
+	let mut starts = vec![0; (nb_columns * nb_rows) as usize + 1];
+	for boid in boids {
+		for cell in around(map[boid.index()]) {
+			starts[cell + 1] += 1;
+		}
+	}
+	for cell in 1..starts.len() {
+		starts[cell] += starts[cell - 1];
+	}
+
+	let mut filled = starts.clone();
+	let mut order = vec![0; *starts.last().unwrap()];
+	for (i, boid) in boids.iter().enumerate() {
+		for cell in around(map[boid.index()]) {
+			order[filled[cell]] = i;
+			filled[cell] += 1;
+		}
+	}
+
+	(map, Cells {
+		rows: nb_rows as usize,
+		starts,
+		boids: order.into_iter().map(|i| boids[i]).collect()
+	})
+}
+
+/// Boids bucketed in the single cell they are in, for searches further than the vision range
+struct Grid<'a> {
+	size: f64,
+	columns: usize,
+	rows: usize,
+	cells: Vec<Vec<Vec<boid::Boid<'a>>>>
+}
+
+impl<'a> Grid<'a> {
+	fn new(canvas: &boid::Canvas, boids: &[boid::Boid<'a>]) -> Grid<'a> {
+		let size = boids
+			.iter()
+			.map(|boid| boid.vision().radius)
+			.fold(f64::NEG_INFINITY, f64::max)
+			.max(1.0);
+		let columns = ((canvas.width / size).ceil() as usize).max(1);
+		let rows = ((canvas.height / size).ceil() as usize).max(1);
+		let mut grid = Grid {
+			size,
+			columns,
+			rows,
+			cells: vec![vec![Vec::new(); rows]; columns]
+		};
+		for boid in boids {
+			let (column, row) = grid.cell(boid);
+			grid.cells[column][row].push(*boid);
+		}
+		grid
+	}
+
+	fn cell(&self, boid: &boid::Boid) -> (usize, usize) {
+		let column = (boid.x() / self.size).floor().max(0.0) as usize;
+		let row = (boid.y() / self.size).floor().max(0.0) as usize;
+		(column.min(self.columns - 1), row.min(self.rows - 1))
+	}
+
+	/// At least the `k` boids closest to `boid` in its field of view, if there are that many.
+	/// Searches rings of cells around the boid's own cell until no closer boid can be left:
+	/// boids beyond ring `n` are at least `n` cells away.
+	fn nearest(&self, boid: &boid::Boid, k: usize) -> Vec<boid::Boid<'a>> {
+		let (column, row) = self.cell(boid);
+		let (column, row) = (column as i64, row as i64);
+		let squared_distance = |other: &boid::Boid| (other.x() - boid.x()).powi(2) + (other.y() - boid.y()).powi(2);
+		let mut found: Vec<(f64, boid::Boid<'a>)> = vec![];
+		let max_ring = self.columns.max(self.rows) as i64;
+		for ring in 0..=max_ring {
+			for delta_column in -ring..=ring {
+				for delta_row in -ring..=ring {
+					if delta_column.abs() != ring && delta_row.abs() != ring {
+						continue
+					}
+					let (target_column, target_row) = (column + delta_column, row + delta_row);
+					if target_column < 0 || target_column >= self.columns as i64 || target_row < 0 || target_row >= self.rows as i64 {
+						continue
+					}
+					for other in &self.cells[target_column as usize][target_row as usize] {
+						if boid.faces(other) {
+							found.push((squared_distance(other), *other));
+						}
+					}
 				}
-				let target_row = row + delta_row;
-				if target_row < 0 || target_row >= nb_rows {
-					continue;
+			}
+			if found.len() >= k {
+				found.sort_by(|a, b| a.0.total_cmp(&b.0));
+				if found[k - 1].0 <= (ring as f64 * self.size).powi(2) {
+					break
 				}
-				cells[target_column as usize][target_row as usize].push(boid)
 			}
 		}
+		found.sort_by(|a, b| a.0.total_cmp(&b.0));
+		found.into_iter().take(k).map(|(_, other)| other).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::arena::Polygon;
+	use crate::space::Vec3;
+	use crate::universe::boid::{Boid, Point};
+
+	/// `count` boids spread over a `width` by `height` canvas
+	fn scattered(width: f64, height: f64, count: usize, seed: u64) -> Universe {
+		let mut universe = Universe::new(None, width, height, seed);
+		universe.flock = Flock::new();
+		universe.add_boids(count);
+		universe
+	}
+
+	/// Squared distances to the `k` nearest boids `boid` faces, by checking them all
+	fn brute_force(boid: &Boid, boids: &[Boid], k: usize) -> Vec<f64> {
+		let mut distances: Vec<f64> = boids
+			.iter()
+			.filter(|other| boid.faces(other))
+			.map(|other| (other.point() - boid.point()).length_squared())
+			.collect();
+		distances.sort_by(f64::total_cmp);
+		distances.truncate(k);
+		distances
+	}
+
+	fn assert_nearest_match(universe: &Universe, k: usize) {
+		let boids: Vec<Boid> = universe.flock.iter().collect();
+		let grid = Grid::new(&universe.canvas, &boids);
+		for boid in &boids {
+			let distances: Vec<f64> = grid.nearest(boid, k).iter().map(|other| (other.point() - boid.point()).length_squared()).collect();
+			assert_eq!(distances, brute_force(boid, &boids, k), "boid {} at {:?}", boid.id(), boid.point());
+		}
+	}
+
+	#[test]
+	fn grid_finds_the_nearest_boids_in_a_crowd() {
+		assert_nearest_match(&scattered(400.0, 300.0, 300, 1), 7);
+	}
 
-		map.insert(boid.id, (column as usize, row as usize));
+	#[test]
+	fn grid_searches_several_rings_in_a_sparse_flock() {
+		// cells are 100 pixels wide, and 10 boids over 1000 by 800 are mostly rings apart
+		let universe = scattered(1000.0, 800.0, 10, 2);
+		assert_eq!(Grid::new(&universe.canvas, &universe.flock.iter().collect::<Vec<_>>()).columns, 10);
+		assert_nearest_match(&universe, 3);
 	}
 
-	(map, cells)
-}
\ No newline at end of file
+	#[test]
+	fn grid_returns_every_boid_in_view_when_k_exceeds_the_flock() {
+		let universe = scattered(400.0, 300.0, 12, 3);
+		assert_nearest_match(&universe, 50);
+	}
+
+	#[test]
+	fn grid_handles_boids_on_cell_edges() {
+		let mut universe = scattered(400.0, 300.0, 0, 4);
+		let mut rng = Rng::new(5);
+		for (x, y) in [(100.0, 100.0), (99.9, 100.0), (200.0, 200.0), (100.0, 0.0), (400.0, 300.0), (300.0, 150.0), (0.0, 0.0)] {
+			universe.flock.spawn(Some(Point::new(x, y)), &universe.canvas, &mut rng);
+		}
+		for k in 1..=7 {
+			assert_nearest_match(&universe, k);
+		}
+	}
+
+	#[test]
+	fn the_space_adds_boids_under_the_click_and_clears_2d_measurements() {
+		let mut universe = scattered(400.0, 300.0, 50, 7);
+		universe.clustering = Some(Clustering::new(crate::cluster::Linkage::Distance));
+		universe.tick(1.0, false);
+		assert!(universe.metrics.average_neighbours > 0.0);
+		assert!(!universe.clustering.as_ref().unwrap().clusters.is_empty());
+
+		universe.set_space(true);
+		universe.add_one_boid_xy(30.0, 40.0);
+		let space = universe.space.as_ref().unwrap();
+		let added = space.boids.last().unwrap();
+		assert!((space.project(added.position).0 - Vec2::new(30.0, 40.0)).length() < 1e-9);
+		universe.tick(1.0, false);
+		assert_eq!(universe.metrics.average_neighbours, 0.0);
+		assert!(universe.clustering.as_ref().unwrap().clusters.is_empty());
+	}
+
+	#[test]
+	fn boids_are_removed_where_they_are_drawn() {
+		let mut universe = scattered(400.0, 300.0, 0, 8);
+		let mut rng = Rng::new(9);
+		universe.flock.spawn(Some(Point::new(100.0, 100.0)), &universe.canvas, &mut rng);
+		assert!(!universe.remove_boid_at(120.0, 100.0, 12.0));
+		assert!(universe.remove_boid_at(105.0, 100.0, 12.0));
+		assert!(universe.flock.is_empty());
+
+		universe.set_space(true);
+		let space = universe.space.as_mut().unwrap();
+		// deep in the box, drawn much closer to the center than its x and y
+		space.add(Vec3::new(20.0, 20.0, 250.0), &mut rng);
+		let (drawn, _) = space.project(Vec3::new(20.0, 20.0, 250.0));
+		assert!(!universe.remove_boid_at(20.0, 20.0, 12.0));
+		assert!(universe.remove_boid_at(drawn.x, drawn.y, 12.0));
+		assert_eq!(universe.boids_count(), 0);
+	}
+
+	#[test]
+	fn added_boids_land_inside_the_arena() {
+		let mut universe = scattered(400.0, 300.0, 0, 10);
+		universe.set_arena(Some(Arena::new(vec![Polygon::new(vec![(10.0, 10.0), (100.0, 10.0), (100.0, 100.0), (10.0, 100.0)])])));
+		universe.add_boids(100);
+		assert_eq!(universe.flock.len(), 100);
+		assert!(universe.flock.iter().all(|boid| universe.canvas.is_open(boid.x(), boid.y())));
+	}
+
+	#[test]
+	fn canvases_thinner_than_the_padding_tick() {
+		let mut universe = Universe::new(None, 10.0, 300.0, 11);
+		for _ in 0..20 {
+			universe.tick(1.0, false);
+		}
+		assert!(universe.flock.iter().all(|boid| (0.0..=10.0).contains(&boid.x()) && (0.0..=300.0).contains(&boid.y())));
+	}
+
+	#[test]
+	fn grid_cells_default_to_a_pixel_without_boids() {
+		let universe = scattered(400.0, 300.0, 0, 6);
+		let grid = Grid::new(&universe.canvas, &[]);
+		assert_eq!((grid.size, grid.columns, grid.rows), (1.0, 400, 300));
+		let (map, _) = grid_split(&universe.canvas, &[]);
+		assert!(map.is_empty());
+	}
+}
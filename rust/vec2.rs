@@ -0,0 +1,209 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
+use crate::angle::Angle;
+
+/// Vector or point on the canvas, y pointing down
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Vec2 {
+	pub x: f64,
+	pub y: f64
+}
+
+impl Vec2 {
+	pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
+
+	pub fn new(x: f64, y: f64) -> Vec2 {
+		Vec2 { x, y }
+	}
+
+	/// Unit vector along which a boid with this heading moves
+	pub fn from_heading(heading: Angle) -> Vec2 {
+		let (x, y) = heading.to_vector();
+		Vec2::new(x, y)
+	}
+
+	/// Heading of a boid moving along this vector. The zero vector gives π
+	pub fn heading(self) -> Angle {
+		Angle::from_vector(self.x, self.y)
+	}
+
+	pub fn dot(self, other: Vec2) -> f64 {
+		self.x * other.x + self.y * other.y
+	}
+
+	/// Z component of the cross product: positive when `other` is clockwise from `self` on the
+	/// canvas, zero when they are parallel
+	pub fn perp_dot(self, other: Vec2) -> f64 {
+		self.x * other.y - self.y * other.x
+	}
+
+	pub fn length_squared(self) -> f64 {
+		self.dot(self)
+	}
+
+	pub fn length(self) -> f64 {
+		self.length_squared().sqrt()
+	}
+
+	pub fn distance(self, other: Vec2) -> f64 {
+		(other - self).length()
+	}
+
+	/// Same direction, unit length. `None` for the zero vector, or one so short that its length underflows
+	pub fn normalise(self) -> Option<Vec2> {
+		let length = self.length();
+		if length == 0.0 {
+			None
+		} else {
+			Some(self / length)
+		}
+	}
+
+	/// Same direction, given length. The zero vector stays as is
+	pub fn with_length(self, length: f64) -> Vec2 {
+		match self.normalise() {
+			Some(direction) => direction * length,
+			None => self
+		}
+	}
+
+	pub fn with_max_length(self, length: f64) -> Vec2 {
+		if self.length() > length {
+			self.with_length(length)
+		} else {
+			self
+		}
+	}
+
+	/// Turns by `radians` the way a heading does: positive turns left on the canvas
+	pub fn rotate(self, radians: f64) -> Vec2 {
+		let (sin, cos) = radians.sin_cos();
+		Vec2::new(self.x * cos + self.y * sin, self.y * cos - self.x * sin)
+	}
+}
+
+impl From<(f64, f64)> for Vec2 {
+	fn from((x, y): (f64, f64)) -> Vec2 {
+		Vec2::new(x, y)
+	}
+}
+
+impl Add for Vec2 {
+	type Output = Vec2;
+	fn add(self, other: Vec2) -> Vec2 {
+		Vec2::new(self.x + other.x, self.y + other.y)
+	}
+}
+
+impl AddAssign for Vec2 {
+	fn add_assign(&mut self, other: Vec2) {
+		*self = *self + other;
+	}
+}
+
+impl Sub for Vec2 {
+	type Output = Vec2;
+	fn sub(self, other: Vec2) -> Vec2 {
+		Vec2::new(self.x - other.x, self.y - other.y)
+	}
+}
+
+impl Mul<f64> for Vec2 {
+	type Output = Vec2;
+	fn mul(self, factor: f64) -> Vec2 {
+		Vec2::new(self.x * factor, self.y * factor)
+	}
+}
+
+impl Div<f64> for Vec2 {
+	type Output = Vec2;
+	fn div(self, divisor: f64) -> Vec2 {
+		Vec2::new(self.x / divisor, self.y / divisor)
+	}
+}
+
+impl Neg for Vec2 {
+	type Output = Vec2;
+	fn neg(self) -> Vec2 {
+		Vec2::new(- self.x, - self.y)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::f64::consts::PI;
+
+	fn assert_close(actual: Vec2, expected: Vec2) {
+		assert!(actual.distance(expected) < 1e-9, "expected {:?} got {:?}", expected, actual);
+	}
+
+	#[test]
+	fn zero_vector_has_no_direction() {
+		assert_eq!(Vec2::ZERO.normalise(), None);
+		assert_eq!(Vec2::ZERO.with_length(3.0), Vec2::ZERO);
+		assert_eq!(Vec2::ZERO.with_max_length(3.0), Vec2::ZERO);
+		assert_eq!(Vec2::ZERO.rotate(1.0), Vec2::ZERO);
+		assert_eq!(Vec2::ZERO.heading(), Angle::new(PI));
+	}
+
+	#[test]
+	fn coincident_points_are_at_zero_distance() {
+		let point = Vec2::new(12.5, -3.0);
+		assert_eq!(point.distance(point), 0.0);
+		assert_eq!((point - point).normalise(), None);
+	}
+
+	#[test]
+	fn vectors_too_short_to_measure_have_no_direction() {
+		// never divides by a length that underflowed to zero
+		assert_eq!(Vec2::new(1e-300, -1e-300).normalise(), None);
+		let short = Vec2::new(1e-150, -1e-150).normalise().unwrap();
+		assert!((short.length() - 1.0).abs() < 1e-12);
+	}
+
+	#[test]
+	fn parallel_vectors_have_zero_perp_dot() {
+		let vector = Vec2::new(2.0, -5.0);
+		assert_eq!(vector.perp_dot(vector * 3.0), 0.0);
+		assert_eq!(vector.perp_dot(- vector), 0.0);
+		assert_eq!(vector.perp_dot(Vec2::ZERO), 0.0);
+	}
+
+	#[test]
+	fn perpendicular_vectors_have_zero_dot() {
+		let vector = Vec2::new(2.0, -5.0);
+		assert_eq!(vector.dot(Vec2::new(5.0, 2.0)), 0.0);
+		assert_eq!(Vec2::new(1.0, 0.0).perp_dot(Vec2::new(0.0, 1.0)), 1.0);
+	}
+
+	#[test]
+	fn with_max_length_only_shortens() {
+		let vector = Vec2::new(3.0, 4.0);
+		assert_eq!(vector.with_max_length(10.0), vector);
+		assert_close(vector.with_max_length(1.0), Vec2::new(0.6, 0.8));
+	}
+
+	#[test]
+	fn rotation_matches_headings() {
+		for &heading in &[0.0, 1.0, PI, 5.0] {
+			for &turn in &[0.3, -2.0, PI * 2.0] {
+				let rotated = Vec2::from_heading(Angle::new(heading)).rotate(turn);
+				assert_close(rotated, Vec2::from_heading(Angle::new(heading + turn)));
+			}
+		}
+	}
+
+	#[test]
+	fn positive_rotation_turns_left_on_the_canvas() {
+		// up the canvas, then left
+		assert_close(Vec2::new(0.0, -1.0).rotate(PI / 2.0), Vec2::new(-1.0, 0.0));
+	}
+
+	#[test]
+	fn headings_round_trip() {
+		for &heading in &[0.0, 0.5, PI, PI * 1.5, PI * 2.0 - 1e-9] {
+			let vector = Vec2::from_heading(Angle::new(heading)) * 7.0;
+			assert!(vector.heading().difference(Angle::new(heading)).abs() < 1e-9);
+		}
+	}
+}